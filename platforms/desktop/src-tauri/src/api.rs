@@ -1,101 +1,517 @@
-use std::fs::File;
-use std::io::{BufWriter, Cursor};
 use std::path::Path;
 
-use mcvm::core::net::download;
-use reqwest::Client;
-use zip::ZipArchive;
+use anyhow::{bail, Context};
+use futures::{stream, StreamExt};
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
 
+use crate::api_cache::ApiCache;
 use crate::api_types::{PackBundle, PackData, PackReference};
+use crate::config::SmithedConfig;
+use crate::hashes::ExpectedHash;
+use crate::http::{DownloadProgress, HttpClient};
+use crate::pack_cache::{CachedPacks, PackCache};
+use crate::weld::{self, WeldConflict, WeldInput, WeldOutput};
+
+/// Client for the Smithed API
+#[derive(Clone)]
+pub struct ApiClient {
+    pub http: HttpClient,
+    base_url: String,
+    cache: Option<ApiCache>,
+}
+
+impl ApiClient {
+    /// Create a client for the API at a base URL
+    pub fn new(http: HttpClient, base_url: &str) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            cache: None,
+        }
+    }
+
+    /// Cache pack and bundle responses
+    pub fn with_cache(mut self, cache: ApiCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Create a client for the API from the `SMITHED_API_URL` environment variable or the
+    /// config, using the public API if neither is set. Requests are retried with the
+    /// retry policy from the config.
+    pub fn from_config(client: Client, config: &SmithedConfig) -> Self {
+        let env_url = std::env::var(API_URL_ENV).ok();
+        let base_url = [env_url.as_deref(), config.api_url.as_deref()]
+            .into_iter()
+            .flatten()
+            .find(|url| !url.is_empty())
+            .unwrap_or(DEFAULT_API_URL);
+        Self::new(HttpClient::new(client, config.retry.clone()), base_url)
+    }
+
+    /// Get the URL of an API endpoint
+    pub fn url(&self, endpoint: &str) -> String {
+        format!("{}{endpoint}", self.base_url)
+    }
+}
 
 /// Get a pack from the API
-pub async fn get_pack(client: &Client, id: &str) -> anyhow::Result<PackData> {
-    let url = format!("{API_URL}/packs/{id}");
-    download::json(url, client).await
+pub async fn get_pack(api: &ApiClient, id: &str) -> anyhow::Result<PackData> {
+    get_json(api, &format!("/packs/{id}")).await
+}
+
+/// Get several packs from the API at the same time, with a limit on how many requests are
+/// made at once. The results are in the same order as the IDs.
+pub async fn get_packs(api: &ApiClient, ids: &[String]) -> Vec<anyhow::Result<PackData>> {
+    stream::iter(ids)
+        .map(|id| get_pack(api, id))
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .collect()
+        .await
 }
 
 /// Get a bundle from the API
-pub async fn get_bundle(client: &Client, id: &str) -> anyhow::Result<PackBundle> {
-    let url = format!("{API_URL}/bundles/{id}");
-    download::json(url, client).await
+pub async fn get_bundle(api: &ApiClient, id: &str) -> anyhow::Result<PackBundle> {
+    get_json(api, &format!("/bundles/{id}")).await
+}
+
+/// Get JSON from an API endpoint. If the client has a cache, fresh responses are used
+/// without a request, and stale ones are used if the API can't be reached.
+async fn get_json<T: DeserializeOwned>(api: &ApiClient, endpoint: &str) -> anyhow::Result<T> {
+    let url = api.url(endpoint);
+    let Some(cache) = &api.cache else {
+        return api.http.get_json(&url).await;
+    };
+    let cached = cache.get(&url);
+    if let Some(cached) = cached.as_ref().filter(|x| cache.is_fresh(x)) {
+        if let Ok(out) = serde_json::from_str(&cached.body) {
+            return Ok(out);
+        }
+    }
+
+    let mut headers = HeaderMap::new();
+    let etag = cached.as_ref().and_then(|x| x.etag.as_deref());
+    if let Some(etag) = etag.and_then(|x| HeaderValue::from_str(x).ok()) {
+        headers.insert(IF_NONE_MATCH, etag);
+    }
+    let result = api
+        .http
+        .get_with(&url, headers, |response| async move {
+            if response.status() == StatusCode::NOT_MODIFIED {
+                return Ok(None);
+            }
+            let response = response.error_for_status()?;
+            let etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|x| x.to_str().ok())
+                .map(str::to_string);
+            let body = response.text().await?;
+            Ok(Some((body, etag)))
+        })
+        .await;
+
+    let body = match (result, cached) {
+        (Ok(Some((body, etag))), _) => {
+            let out = serde_json::from_str(&body).context("Failed to parse API response")?;
+            if let Err(e) = cache.insert(&url, body, etag) {
+                eprintln!("Failed to cache API response: {e:?}");
+            }
+            return Ok(out);
+        }
+        (Ok(None), Some(cached)) => {
+            if let Err(e) = cache.revalidate(&url) {
+                eprintln!("Failed to update cached API response: {e:?}");
+            }
+            cached.body
+        }
+        (Ok(None), None) => bail!("The API said that a response that isn't cached didn't change"),
+        // Only fall back when the API can't be reached, not when it says the data is gone
+        (Err(e), Some(cached))
+            if e.status().map_or(true, |x| {
+                x.is_server_error() || x == StatusCode::TOO_MANY_REQUESTS
+            }) =>
+        {
+            eprintln!("Using cached response because the API could not be reached: {e}");
+            cached.body
+        }
+        (Err(e), _) => return Err(e.into()),
+    };
+
+    serde_json::from_str(&body).context("Failed to parse cached API response")
 }
 
-/// Download and weld packs from the API
+/// Download packs from the API and weld them together, reusing welded packs from the cache
+/// when possible. Returns the conflicts that were found while welding.
 pub async fn download_packs(
-    client: &Client,
+    api: &ApiClient,
     packs: Vec<PackReference>,
+    minecraft_version: &str,
+    cache: &PackCache,
     datapacks_dir: &Path,
     resource_packs_dir: &Path,
-) -> anyhow::Result<()> {
-    if packs.is_empty() {
-        return Ok(());
-    }
-
-    let url = format_download_packs_url(packs);
-
-    let bytes = download::bytes(url, client).await?;
-    let mut cursor = Cursor::new(bytes);
-    let mut zip = ZipArchive::new(&mut cursor)?;
-    for i in 0..zip.len() {
-        let mut file = zip.by_index(i)?;
-        let path = if file.name().contains("resource") {
-            Some(resource_packs_dir)
-        } else if file.name().contains("datapack") {
-            Some(datapacks_dir)
-        } else {
-            None
+    progress: &impl DownloadProgress,
+) -> anyhow::Result<Vec<WeldConflict>> {
+    if let Some(conflicts) = install_cached_packs(
+        &packs,
+        minecraft_version,
+        cache,
+        datapacks_dir,
+        resource_packs_dir,
+    )? {
+        println!("Using cached welded packs");
+        return Ok(conflicts);
+    }
+
+    let key = PackCache::key(&packs, minecraft_version);
+    let mut datapacks = Vec::new();
+    let mut resource_packs = Vec::new();
+    for pack in &packs {
+        let data = get_pack(api, &pack.id)
+            .await
+            .with_context(|| format!("Failed to get pack '{}'", pack.id))?;
+        let Some(version) = data.get_version(&pack.version) else {
+            bail!("Pack '{}' has no version '{}'", pack.id, pack.version);
         };
-        if let Some(path) = path {
-            let out_file = File::create(path.join(WELDED_PACK_FILENAME))?;
-            let mut out_file = BufWriter::new(out_file);
-            std::io::copy(&mut file, &mut out_file)?;
+        let hashes = version.hashes.as_ref();
+        let downloads = [
+            (
+                "datapack",
+                &version.downloads.datapack,
+                hashes.and_then(|x| x.datapack.as_ref()),
+                &mut datapacks,
+            ),
+            (
+                "resource pack",
+                &version.downloads.resource_pack,
+                hashes.and_then(|x| x.resource_pack.as_ref()),
+                &mut resource_packs,
+            ),
+        ];
+        for (kind, url, hash, out) in downloads {
+            if let Some(url) = url {
+                let name = format!("{} {kind}", pack.id);
+                let bytes = api
+                    .http
+                    .download(url, &name, progress)
+                    .await
+                    .with_context(|| format!("Failed to download pack '{}'", pack.id))?;
+                if let Some(hash) = hash {
+                    ExpectedHash::Sha1(hash.clone())
+                        .verify(&bytes, &format!("{}@{}", pack.id, pack.version))?;
+                }
+                out.push(WeldInput {
+                    name: pack.id.clone(),
+                    data: bytes.to_vec(),
+                });
+            }
         }
     }
 
-    Ok(())
+    let datapack = weld_packs(&datapacks).context("Failed to weld datapacks")?;
+    let resource_pack = weld_packs(&resource_packs).context("Failed to weld resource packs")?;
+    let conflicts: Vec<_> = datapack
+        .iter()
+        .chain(resource_pack.iter())
+        .flat_map(|output| output.conflicts.iter().cloned())
+        .collect();
+
+    let cached = cache
+        .insert(
+            &key,
+            &packs,
+            minecraft_version,
+            datapack.as_ref().map(|output| output.data.as_slice()),
+            resource_pack.as_ref().map(|output| output.data.as_slice()),
+            &conflicts,
+        )
+        .context("Failed to cache welded packs")?;
+
+    install_welded_packs(&cached, datapacks_dir, resource_packs_dir)?;
+
+    Ok(cached.conflicts)
+}
+
+/// Install welded packs from the cache without using the network.
+/// Returns the conflicts from welding, or None if the packs are not cached.
+pub fn install_cached_packs(
+    packs: &[PackReference],
+    minecraft_version: &str,
+    cache: &PackCache,
+    datapacks_dir: &Path,
+    resource_packs_dir: &Path,
+) -> anyhow::Result<Option<Vec<WeldConflict>>> {
+    let cached = if packs.is_empty() {
+        CachedPacks::default()
+    } else if let Some(cached) = cache.get(&PackCache::key(packs, minecraft_version)) {
+        cached
+    } else {
+        return Ok(None);
+    };
+    install_welded_packs(&cached, datapacks_dir, resource_packs_dir)?;
+
+    Ok(Some(cached.conflicts))
 }
 
-fn format_download_packs_url(packs: Vec<PackReference>) -> String {
-    let mut url = format!("{API_URL}/download");
-    if !packs.is_empty() {
-        url.push('?');
+/// Weld a list of packs, returning nothing if there are no packs to weld
+fn weld_packs(packs: &[WeldInput]) -> anyhow::Result<Option<WeldOutput>> {
+    if packs.is_empty() {
+        Ok(None)
+    } else {
+        weld::weld(packs).map(Some)
     }
+}
 
-    for (i, pack) in packs.iter().enumerate() {
-        let mut param = String::new();
-        if i > 0 {
-            param.push('&');
+/// Copy welded packs into the pack directories, removing stale welded packs that are no longer used
+fn install_welded_packs(
+    packs: &CachedPacks,
+    datapacks_dir: &Path,
+    resource_packs_dir: &Path,
+) -> anyhow::Result<()> {
+    for (src, dir) in [
+        (&packs.datapack, datapacks_dir),
+        (&packs.resource_pack, resource_packs_dir),
+    ] {
+        let dest = dir.join(WELDED_PACK_FILENAME);
+        if let Some(src) = src {
+            // Skip copying the pack if the installed one is already the same
+            if ExpectedHash::Sha1(src.sha1.clone()).matches_file(&dest) {
+                continue;
+            }
+            std::fs::copy(&src.path, &dest).context("Failed to copy welded pack")?;
+        } else if dest.exists() {
+            std::fs::remove_file(&dest).context("Failed to remove old welded pack")?;
         }
-        param.push_str(&format!("pack={}@{}", pack.id, pack.version));
-        url.push_str(&param);
     }
 
-    url
+    Ok(())
 }
 
-/// The API URL
-pub const API_URL: &str = "https://api.smithed.dev/v2";
+/// The URL of the public Smithed API
+pub const DEFAULT_API_URL: &str = "https://api.smithed.dev/v2";
+/// Environment variable to use another API, like a staging or self-hosted one
+pub const API_URL_ENV: &str = "SMITHED_API_URL";
+/// How many requests to make to the API at the same time
+const MAX_CONCURRENT_REQUESTS: usize = 8;
 
 /// The filename of the welded pack
 pub const WELDED_PACK_FILENAME: &str = "SmithedWeldedPack.zip";
 
 #[cfg(test)]
 mod tests {
+    use std::io::{Cursor, Write};
+    use std::time::Duration;
+
+    use serde_json::json;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
     use super::*;
+    use crate::hashes;
+    use crate::http::RetryPolicy;
+    use crate::test_server::{StubResponse, StubServer};
 
-    #[test]
-    fn test_download_packs_url_format() {
-        let packs = vec![
-            PackReference {
-                id: "foo".into(),
-                version: "1".into(),
-            },
-            PackReference {
-                id: "bar".into(),
-                version: "1.2.5".into(),
+    fn make_pack(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, contents) in files {
+            zip.start_file(*path, FileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn pack_data(id: &str, datapack_url: &str, sha1: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "versions": [{
+                "name": "1.0.0",
+                "downloads": {"datapack": datapack_url},
+                "supports": ["1.20.4"],
+                "dependencies": [],
+                "hashes": {"datapack": sha1},
+            }],
+            "display": {
+                "name": id,
+                "description": "",
+                "icon": "",
+                "hidden": false,
             },
-        ];
-        let url = format_download_packs_url(packs);
-        assert_eq!(url, format!("{API_URL}/download?pack=foo@1&pack=bar@1.2.5"))
+        })
+    }
+
+    fn test_http() -> HttpClient {
+        let retry = RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        };
+        HttpClient::new(Client::new(), retry)
+    }
+
+    fn pack_ref(id: &str) -> PackReference {
+        PackReference {
+            id: id.into(),
+            version: "1.0.0".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_pack_and_bundle() {
+        let server = StubServer::start();
+        let api = ApiClient::new(test_http(), &format!("{}/", server.url()));
+        server.route("/packs/foo", StubResponse::json(&pack_data("foo", "", "")));
+        server.route(
+            "/bundles/bar",
+            StubResponse::json(&json!({
+                "owner": "someone",
+                "name": "Bar",
+                "version": "1.20.4",
+                "packs": [{"id": "foo", "version": "1.0.0"}],
+                "public": true,
+                "uid": "bar",
+            })),
+        );
+
+        let pack = get_pack(&api, "foo").await.unwrap();
+        assert_eq!(pack.id, "foo");
+        assert_eq!(pack.versions[0].supports, vec!["1.20.4".to_string()]);
+        let bundle = get_bundle(&api, "bar").await.unwrap();
+        assert_eq!(bundle.packs, vec![pack_ref("foo")]);
+
+        assert!(get_pack(&api, "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cached_responses() {
+        let server = StubServer::start();
+        let dir = tempfile::tempdir().unwrap();
+        let cache = |ttl| ApiCache::new(dir.path().to_path_buf(), ttl);
+        let api =
+            ApiClient::new(test_http(), server.url()).with_cache(cache(Duration::from_secs(60)));
+        server
+            .route(
+                "/packs/foo",
+                StubResponse::json(&pack_data("foo", "", "")).header("ETag", "\"v1\""),
+            )
+            .route("/packs/foo", StubResponse::status(304));
+
+        // Fresh responses don't make a request
+        get_pack(&api, "foo").await.unwrap();
+        get_pack(&api, "foo").await.unwrap();
+        assert_eq!(server.requests("/packs/foo").len(), 1);
+
+        // Stale responses are revalidated with their ETag
+        let api = api.with_cache(cache(Duration::ZERO));
+        assert_eq!(get_pack(&api, "foo").await.unwrap().id, "foo");
+        let requests = server.requests("/packs/foo");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[1].headers.get("if-none-match").map(String::as_str),
+            Some("\"v1\"")
+        );
+
+        // Stale responses are used when the API is down, but not when the pack is gone
+        server
+            .route("/packs/bar", StubResponse::json(&pack_data("bar", "", "")))
+            .route("/packs/bar", StubResponse::status(503));
+        server
+            .route(
+                "/packs/gone",
+                StubResponse::json(&pack_data("gone", "", "")),
+            )
+            .route("/packs/gone", StubResponse::status(404));
+        for _ in 0..2 {
+            assert_eq!(get_pack(&api, "bar").await.unwrap().id, "bar");
+        }
+        assert!(get_pack(&api, "gone").await.is_ok());
+        assert!(get_pack(&api, "gone").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_packs() {
+        let server = StubServer::start();
+        let api = ApiClient::new(test_http(), server.url());
+        let ids: Vec<String> = (0..20).map(|i| format!("pack{i}")).collect();
+        for id in &ids {
+            if id != "pack3" {
+                server.route(
+                    &format!("/packs/{id}"),
+                    StubResponse::json(&pack_data(id, "", "")),
+                );
+            }
+        }
+
+        let results = get_packs(&api, &ids).await;
+        assert_eq!(results.len(), ids.len());
+        for (id, result) in ids.iter().zip(results) {
+            if id == "pack3" {
+                assert!(result.is_err());
+            } else {
+                assert_eq!(&result.unwrap().id, id);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_packs() {
+        let server = StubServer::start();
+        let api = ApiClient::new(test_http(), server.url());
+        let datapack = make_pack(&[
+            (
+                "pack.mcmeta",
+                r#"{"pack":{"pack_format":26,"description":"Foo"}}"#,
+            ),
+            ("data/foo/functions/load.mcfunction", "say foo"),
+        ]);
+        let download_url = format!("{}/downloads/foo.zip", server.url());
+        server.route(
+            "/packs/foo",
+            StubResponse::json(&pack_data("foo", &download_url, &hashes::sha1(&datapack))),
+        );
+        server.route("/downloads/foo.zip", StubResponse::bytes(datapack));
+        server.route(
+            "/packs/bad",
+            StubResponse::json(&pack_data("bad", &download_url, &"0".repeat(40))),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PackCache::with_dir(dir.path().join("cache"), u64::MAX);
+        let datapacks_dir = dir.path().join("datapacks");
+        let resource_packs_dir = dir.path().join("resourcepacks");
+        std::fs::create_dir_all(&datapacks_dir).unwrap();
+        std::fs::create_dir_all(&resource_packs_dir).unwrap();
+
+        for _ in 0..2 {
+            let conflicts = download_packs(
+                &api,
+                vec![pack_ref("foo")],
+                "1.20.4",
+                &cache,
+                &datapacks_dir,
+                &resource_packs_dir,
+                &(),
+            )
+            .await
+            .unwrap();
+            assert!(conflicts.is_empty());
+            assert!(datapacks_dir.join(WELDED_PACK_FILENAME).exists());
+            assert!(!resource_packs_dir.join(WELDED_PACK_FILENAME).exists());
+        }
+        // The second install comes from the pack cache
+        assert_eq!(server.requests("/packs/foo").len(), 1);
+        assert_eq!(server.requests("/downloads/foo.zip").len(), 1);
+
+        let result = download_packs(
+            &api,
+            vec![pack_ref("bad")],
+            "1.20.4",
+            &cache,
+            &datapacks_dir,
+            &resource_packs_dir,
+            &(),
+        )
+        .await;
+        assert!(result.is_err());
     }
 }
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::files;
+
+/// Cache for API responses, kept in memory and on disk so that it lasts between launches
+#[derive(Clone)]
+pub struct ApiCache {
+    dir: PathBuf,
+    ttl: Duration,
+    memory: Arc<Mutex<HashMap<String, CachedResponse>>>,
+}
+
+/// A response from the API
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CachedResponse {
+    pub body: String,
+    /// The ETag of the response, used to check if it changed without downloading it again
+    pub etag: Option<String>,
+    /// Unix timestamp of when the response was last fetched or revalidated
+    pub fetched_at: i64,
+}
+
+impl ApiCache {
+    /// Creates the cache in a directory, with how long responses are used before they
+    /// are checked again
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self {
+            dir,
+            ttl,
+            memory: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Get the cached response for a URL, even if it is stale
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let mut memory = self.memory.lock().ok()?;
+        if let Some(response) = memory.get(url) {
+            return Some(response.clone());
+        }
+        let text = std::fs::read_to_string(self.path(url)).ok()?;
+        let response: CachedResponse = serde_json::from_str(&text).ok()?;
+        memory.insert(url.to_string(), response.clone());
+
+        Some(response)
+    }
+
+    /// Store the response for a URL
+    pub fn insert(&self, url: &str, body: String, etag: Option<String>) -> anyhow::Result<()> {
+        let response = CachedResponse {
+            body,
+            etag,
+            fetched_at: Utc::now().timestamp(),
+        };
+        self.store(url, response)
+    }
+
+    /// Mark the cached response for a URL as fresh again after the API said it didn't change
+    pub fn revalidate(&self, url: &str) -> anyhow::Result<()> {
+        let Some(mut response) = self.get(url) else {
+            return Ok(());
+        };
+        response.fetched_at = Utc::now().timestamp();
+        self.store(url, response)
+    }
+
+    /// Whether a cached response can be used without checking with the API
+    pub fn is_fresh(&self, response: &CachedResponse) -> bool {
+        let age = Utc::now().timestamp() - response.fetched_at;
+        age >= 0 && (age as u64) < self.ttl.as_secs()
+    }
+
+    /// Remove responses from disk that haven't been fetched for longer than the max age,
+    /// along with any that can't be read, so that the cache doesn't grow forever
+    pub fn evict(&self, max_age: Duration) -> anyhow::Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+
+        let now = Utc::now().timestamp();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|x| x.to_str()) != Some("json") {
+                continue;
+            }
+            let fetched_at = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|text| serde_json::from_str::<CachedResponse>(&text).ok())
+                .map(|response| response.fetched_at);
+            let expired = match fetched_at {
+                Some(fetched_at) => now.saturating_sub(fetched_at) as u64 > max_age.as_secs(),
+                None => true,
+            };
+            if expired {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        if let Ok(mut memory) = self.memory.lock() {
+            memory.retain(|_, response| {
+                now.saturating_sub(response.fetched_at) as u64 <= max_age.as_secs()
+            });
+        }
+
+        Ok(())
+    }
+
+    fn store(&self, url: &str, response: CachedResponse) -> anyhow::Result<()> {
+        let text = serde_json::to_string(&response).context("Failed to serialize response")?;
+        if let Ok(mut memory) = self.memory.lock() {
+            memory.insert(url.to_string(), response);
+        }
+        std::fs::create_dir_all(&self.dir).context("Failed to create API cache directory")?;
+        files::write_replace(&self.path(url), text.as_bytes())
+            .context("Failed to write cached response")
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        let hash = hex::encode(Sha256::digest(url.as_bytes()));
+        self.dir.join(format!("{hash}.json"))
+    }
+}
+
+/// How long API responses are used before they are checked again
+pub const API_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How long API responses are kept on disk after they were last fetched
+pub const API_CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ApiCache::new(dir.path().to_path_buf(), Duration::from_secs(60));
+        assert!(cache.get("https://example.com/packs/foo").is_none());
+        cache
+            .insert(
+                "https://example.com/packs/foo",
+                "{}".into(),
+                Some("\"abc\"".into()),
+            )
+            .unwrap();
+        let response = cache.get("https://example.com/packs/foo").unwrap();
+        assert!(cache.is_fresh(&response));
+
+        // A new cache in the same directory loads the response from disk
+        let cache = ApiCache::new(dir.path().to_path_buf(), Duration::ZERO);
+        let response = cache.get("https://example.com/packs/foo").unwrap();
+        assert_eq!(response.etag.as_deref(), Some("\"abc\""));
+        assert!(!cache.is_fresh(&response));
+    }
+
+    #[test]
+    fn test_evict() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ApiCache::new(dir.path().to_path_buf(), Duration::from_secs(60));
+        cache
+            .insert("https://example.com/packs/new", "{}".into(), None)
+            .unwrap();
+        let old = CachedResponse {
+            body: "{}".into(),
+            etag: None,
+            fetched_at: Utc::now().timestamp() - 2 * 60 * 60,
+        };
+        cache.store("https://example.com/packs/old", old).unwrap();
+        let broken = dir.path().join("broken.json");
+        std::fs::write(&broken, "{").unwrap();
+
+        cache.evict(Duration::from_secs(60 * 60)).unwrap();
+        assert!(!broken.exists());
+        assert!(!cache.path("https://example.com/packs/old").exists());
+        assert!(cache.get("https://example.com/packs/old").is_none());
+        assert!(cache.get("https://example.com/packs/new").is_some());
+    }
+}
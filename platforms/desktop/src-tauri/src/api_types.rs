@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// A reference to a pack and its version, contained in a bundle
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct PackReference {
     pub id: String,
     pub version: String,
@@ -43,6 +43,8 @@ pub struct PackVersion {
     pub downloads: PackVersionDownloads,
     pub supports: Vec<String>,
     pub dependencies: Option<Vec<PackReference>>,
+    #[serde(default)]
+    pub hashes: Option<PackVersionHashes>,
 }
 
 /// Download links for a pack version
@@ -53,6 +55,14 @@ pub struct PackVersionDownloads {
     pub resource_pack: Option<String>,
 }
 
+/// SHA-1 hashes of the downloads for a pack version
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PackVersionHashes {
+    pub datapack: Option<String>,
+    #[serde(rename = "resourcepack")]
+    pub resource_pack: Option<String>,
+}
+
 impl PackData {
     /// Get the newest version of this pack that supports a given Minecraft version
     pub fn get_newest_version(&self, minecraft_version: &str) -> Option<&PackVersion> {
@@ -63,6 +73,11 @@ impl PackData {
                 .any(|supported| supported == minecraft_version)
         })
     }
+
+    /// Get a version of this pack by its name
+    pub fn get_version(&self, name: &str) -> Option<&PackVersion> {
+        self.versions.iter().find(|vers| vers.name == name)
+    }
 }
 
 /// Data for a bundle
@@ -1,15 +1,37 @@
-use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
 
-use anyhow::Context;
+use anyhow::{anyhow, bail, Context};
 use directories::ProjectDirs;
+use fs2::FileExt;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 use crate::api_types::PackReference;
+use crate::files;
+use crate::http::RetryPolicy;
+use crate::paths;
 
 /// Smithed configuration file
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct SmithedConfig {
+    /// The version of the config format. Older configs are migrated when they are opened.
+    pub schema_version: u32,
     pub local_bundles: HashMap<String, LocalBundleConfig>,
+    /// How many seconds to wait for the game to exit when stopping it before killing it
+    #[serde(default = "default_stop_grace_period")]
+    pub stop_grace_period: u64,
+    /// The Smithed API to use instead of the public one. Only read when the launcher starts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+    /// How failed requests to the API and mod downloads are retried
+    #[serde(default)]
+    pub retry: RetryPolicy,
 }
 
 /// Config for a  local launchable bundle
@@ -20,42 +42,397 @@ pub struct LocalBundleConfig {
 }
 
 impl SmithedConfig {
-    /// Opens the config at the default path
-    pub fn open(dirs: &ProjectDirs) -> anyhow::Result<SmithedConfig> {
-        let path = Self::path(dirs);
+    /// Opens the config at a path, migrating it if it is from an older version.
+    /// The original file is backed up before a migrated config is written.
+    /// If the config is corrupt, the backup from the last write is restored.
+    fn open_path(path: &Path) -> anyhow::Result<SmithedConfig> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        if path.exists() {
-            let file = File::open(path).context("Failed to open config file")?;
-            let mut file = BufReader::new(file);
-            let out = serde_json::from_reader(&mut file).context("Failed to parse config file")?;
-            Ok(out)
-        } else {
-            Ok(Self::default())
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let (text, mut document) = match read_document(path) {
+            Ok(document) => document,
+            Err(e) => restore_backup(path).map_err(|_| e)?,
+        };
+        let version = document
+            .get("schema_version")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        if version > CURRENT_SCHEMA_VERSION as u64 {
+            bail!(
+                "Config version {version} is newer than the supported version {CURRENT_SCHEMA_VERSION}. Update the launcher to use it"
+            );
+        }
+
+        let migrated = version < CURRENT_SCHEMA_VERSION as u64;
+        if migrated {
+            let backup = migration_backup_path(path, version);
+            std::fs::write(&backup, &text).context("Failed to back up config file")?;
+            println!(
+                "Migrating config from version {version}. The old config was backed up to {}",
+                backup.display()
+            );
+            migrate(&mut document, version as usize)?;
+        }
+
+        let out: Self = serde_json::from_value(Value::Object(document))
+            .context("Failed to parse config file")?;
+        if migrated {
+            out.write_path(path)
+                .context("Failed to write migrated config")?;
         }
+
+        Ok(out)
     }
 
-    /// Writes the config to the config file
-    pub fn write(self, dirs: &ProjectDirs) -> anyhow::Result<()> {
+    /// Write the config without leaving a partially written file if the write is interrupted
+    fn write_path(&self, path: &Path) -> anyhow::Result<()> {
         let text = serde_json::to_string_pretty(&self).context("Failed to serialize config")?;
-
-        let path = Self::path(dirs);
-        std::fs::write(path, text).context("Failed to write config file")?;
+        files::write_atomic(path, text.as_bytes()).context("Failed to write config file")?;
 
         Ok(())
     }
 
     /// Get the path to the config
     fn path(dirs: &ProjectDirs) -> PathBuf {
-        dirs.config_dir().join("smithed.json")
+        paths::config_file(dirs)
     }
 }
 
+/// The config shared by all of the commands. The config file is locked while it is read
+/// or changed, so that commands and other launcher instances that change it at the same
+/// time don't lose each other's changes.
+pub struct ConfigStore {
+    path: PathBuf,
+    /// The config, once it has been loaded
+    config: Mutex<Option<CachedConfig>>,
+}
+
+/// The config as it was last read or written, with the state of the file at that time
+struct CachedConfig {
+    config: SmithedConfig,
+    stamp: Option<FileStamp>,
+}
+
+/// When the config file was last changed and its length, used to tell if another
+/// launcher instance has written it since it was read
+type FileStamp = (SystemTime, u64);
+
+impl ConfigStore {
+    /// Create the store for the config at the default path. The config is loaded when it is first used.
+    pub fn new(dirs: &ProjectDirs) -> Self {
+        Self::with_path(SmithedConfig::path(dirs))
+    }
+
+    /// Create the store for the config at a custom path
+    pub fn with_path(path: PathBuf) -> Self {
+        Self {
+            path,
+            config: Mutex::new(None),
+        }
+    }
+
+    /// Get a copy of the config. The file is read again if it has changed since it was last used.
+    pub async fn get(&self) -> anyhow::Result<SmithedConfig> {
+        if let Some(config) = self.cached()? {
+            return Ok(config);
+        }
+        let _file_lock = self.lock_file().await?;
+        let config = SmithedConfig::open_path(&self.path)?;
+        self.cache(config.clone())?;
+
+        Ok(config)
+    }
+
+    /// Change the config and write it. The config is read from the file again first,
+    /// so that changes from other launcher instances are kept. If the function fails,
+    /// nothing is changed.
+    pub async fn update<T>(
+        &self,
+        f: impl FnOnce(&mut SmithedConfig) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let _file_lock = self.lock_file().await?;
+        let mut config = SmithedConfig::open_path(&self.path)?;
+        let out = f(&mut config)?;
+        config.write_path(&self.path)?;
+        self.cache(config)?;
+
+        Ok(out)
+    }
+
+    /// Get the cached config if the file hasn't changed since it was cached
+    fn cached(&self) -> anyhow::Result<Option<SmithedConfig>> {
+        let lock = self.config.lock().map_err(|e| anyhow!("{e}"))?;
+        Ok(lock
+            .as_ref()
+            .filter(|cached| cached.stamp == self.file_stamp())
+            .map(|cached| cached.config.clone()))
+    }
+
+    /// Cache the config that was just read or written
+    fn cache(&self, config: SmithedConfig) -> anyhow::Result<()> {
+        let mut lock = self.config.lock().map_err(|e| anyhow!("{e}"))?;
+        *lock = Some(CachedConfig {
+            config,
+            stamp: self.file_stamp(),
+        });
+
+        Ok(())
+    }
+
+    /// Get the current stamp of the config file, or None if it doesn't exist
+    fn file_stamp(&self) -> Option<FileStamp> {
+        let meta = std::fs::metadata(&self.path).ok()?;
+        Some((meta.modified().ok()?, meta.len()))
+    }
+
+    /// Lock the config file until the returned file is dropped. The lock is polled so that
+    /// waiting for another launcher instance doesn't block the async runtime, and it gives
+    /// up after a while instead of waiting forever.
+    async fn lock_file(&self) -> anyhow::Result<File> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(files::with_suffix(&self.path, ".lock"))
+            .context("Failed to open config lock file")?;
+        let start = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(file),
+                Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+                    if start.elapsed() >= LOCK_TIMEOUT {
+                        bail!("Timed out waiting for another launcher to release the config file");
+                    }
+                    tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+                }
+                Err(e) => return Err(e).context("Failed to lock config file"),
+            }
+        }
+    }
+}
+
+/// How long to wait for the config file lock before failing
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait between attempts to take the config file lock
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
 impl Default for SmithedConfig {
     fn default() -> Self {
         SmithedConfig {
+            schema_version: CURRENT_SCHEMA_VERSION,
             local_bundles: HashMap::new(),
+            stop_grace_period: default_stop_grace_period(),
+            api_url: None,
+            retry: RetryPolicy::default(),
         }
     }
 }
+
+fn default_stop_grace_period() -> u64 {
+    10
+}
+
+/// A migration that upgrades a config document by one version
+type Migration = fn(&mut Map<String, Value>) -> anyhow::Result<()>;
+
+/// Migrations in order, where the migration at an index upgrades configs of that version
+const MIGRATIONS: [Migration; CURRENT_SCHEMA_VERSION as usize] = [migrate_v0];
+
+/// The version of the config format that this version of the launcher writes
+const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+/// Run all of the migrations needed to upgrade a config document from a version
+fn migrate(document: &mut Map<String, Value>, from_version: usize) -> anyhow::Result<()> {
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from_version) {
+        migration(document)
+            .with_context(|| format!("Failed to migrate config from version {version}"))?;
+        document.insert("schema_version".into(), (version + 1).into());
+    }
+
+    Ok(())
+}
+
+/// Configs from before the version field only had the local bundles, which could be missing
+fn migrate_v0(document: &mut Map<String, Value>) -> anyhow::Result<()> {
+    let bundles = document
+        .entry("local_bundles")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if bundles.is_null() {
+        *bundles = Value::Object(Map::new());
+    }
+    if !bundles.is_object() {
+        bail!("local_bundles is not an object");
+    }
+
+    Ok(())
+}
+
+/// Read a config document
+fn read_document(path: &Path) -> anyhow::Result<(String, Map<String, Value>)> {
+    let text = std::fs::read_to_string(path).context("Failed to open config file")?;
+    let document: Value = serde_json::from_str(&text).context("Failed to parse config file")?;
+    let Value::Object(document) = document else {
+        bail!("Config file is not an object");
+    };
+
+    Ok((text, document))
+}
+
+/// Replace a corrupt config with its backup, keeping the corrupt file around to look at
+fn restore_backup(path: &Path) -> anyhow::Result<(String, Map<String, Value>)> {
+    let (text, document) = read_document(&files::backup_path(path))?;
+    eprintln!("Config file is corrupt, restoring the backup");
+    let _ = std::fs::rename(path, files::with_suffix(path, ".corrupt"));
+    files::write_replace(path, text.as_bytes()).context("Failed to restore config backup")?;
+
+    Ok((text, document))
+}
+
+/// Where to back up a config of a version before it is migrated
+fn migration_backup_path(path: &Path, version: u64) -> PathBuf {
+    files::with_suffix(path, &format!(".v{version}.bak"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_v0() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("smithed.json");
+        let original = r#"{"local_bundles": {"foo": {"version": "1.20.4", "packs": []}}}"#;
+        std::fs::write(&path, original).unwrap();
+
+        let config = SmithedConfig::open_path(&path).unwrap();
+        assert_eq!(config.schema_version, CURRENT_SCHEMA_VERSION);
+        assert!(config.local_bundles.contains_key("foo"));
+        let backup = std::fs::read_to_string(migration_backup_path(&path, 0)).unwrap();
+        assert_eq!(backup, original);
+        let written: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["schema_version"], CURRENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migration_failure_keeps_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("smithed.json");
+        let original = r#"{"local_bundles": []}"#;
+        std::fs::write(&path, original).unwrap();
+
+        assert!(SmithedConfig::open_path(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+        assert_eq!(
+            std::fs::read_to_string(migration_backup_path(&path, 0)).unwrap(),
+            original
+        );
+
+        std::fs::write(&path, r#"{"schema_version": 1000, "local_bundles": {}}"#).unwrap();
+        assert!(SmithedConfig::open_path(&path).is_err());
+    }
+
+    #[test]
+    fn test_restore_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("smithed.json");
+        let mut config = SmithedConfig::default();
+        config.write_path(&path).unwrap();
+        config.stop_grace_period = 20;
+        config.write_path(&path).unwrap();
+
+        // Only the backup of the first write is left after the config is truncated
+        std::fs::write(&path, r#"{"schema_version": 1, "local_bu"#).unwrap();
+        let config = SmithedConfig::open_path(&path).unwrap();
+        assert_eq!(config.stop_grace_period, 10);
+        assert!(SmithedConfig::open_path(&path).is_ok());
+        assert!(files::with_suffix(&path, ".corrupt").exists());
+    }
+
+    /// Run a future on a runtime of its own, like a command on another thread would
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn add_bundle(config: &mut SmithedConfig, id: String) {
+        let bundle = LocalBundleConfig {
+            version: "1.20.4".into(),
+            packs: Vec::new(),
+        };
+        config.local_bundles.insert(id, bundle);
+    }
+
+    #[test]
+    fn test_concurrent_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("smithed.json");
+        let store = ConfigStore::with_path(path.clone());
+        // Another launcher instance writing the same file
+        let other = ConfigStore::with_path(path);
+
+        std::thread::scope(|scope| {
+            for i in 0..20 {
+                let store = if i % 2 == 0 { &store } else { &other };
+                scope.spawn(move || {
+                    block_on(store.update(|config| {
+                        add_bundle(config, format!("bundle{i}"));
+                        Ok(())
+                    }))
+                    .unwrap();
+                });
+            }
+        });
+        let count = block_on(store.update(|config| Ok(config.local_bundles.len())));
+        assert_eq!(count.unwrap(), 20);
+
+        let result: anyhow::Result<()> = block_on(store.update(|config| {
+            config.local_bundles.clear();
+            bail!("Failed")
+        }));
+        assert!(result.is_err());
+        assert_eq!(block_on(store.get()).unwrap().local_bundles.len(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_get_sees_other_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("smithed.json");
+        let store = ConfigStore::with_path(path.clone());
+        let other = ConfigStore::with_path(path);
+
+        assert!(store.get().await.unwrap().local_bundles.is_empty());
+        other
+            .update(|config| {
+                add_bundle(config, "foo".into());
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert!(store.get().await.unwrap().local_bundles.contains_key("foo"));
+    }
+
+    #[tokio::test]
+    async fn test_lock_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ConfigStore::with_path(dir.path().join("smithed.json"));
+        let held = store.lock_file().await.unwrap();
+
+        // Another launcher instance holding the lock makes updates fail instead of hanging
+        let other = ConfigStore::with_path(dir.path().join("smithed.json"));
+        assert!(other.update(|_| Ok(())).await.is_err());
+        drop(held);
+        assert!(other.update(|_| Ok(())).await.is_ok());
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api_types::{PackData, PackReference, PackVersion};
+
+/// The result of resolving the dependencies of a pack
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DependencyResolution {
+    /// Packs to add to the bundle, with dependencies before the packs that need them
+    pub added: Vec<PackReference>,
+    pub conflicts: Vec<DependencyConflict>,
+    /// Packs that are added with a different version than the one that was asked for,
+    /// because that version doesn't support the bundle's Minecraft version
+    pub substituted: Vec<VersionSubstitution>,
+    /// Dependency cycles that were found, as lists of pack IDs
+    pub cycles: Vec<Vec<String>>,
+}
+
+/// A problem with a dependency that prevents it from being added
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DependencyConflict {
+    /// Two packs need different versions of the same pack
+    VersionMismatch {
+        pack_id: String,
+        selected: String,
+        required: String,
+        required_by: Option<String>,
+    },
+    /// No version of the pack supports the bundle's Minecraft version
+    NoCompatibleVersion {
+        pack_id: String,
+        required_by: Option<String>,
+    },
+    /// The pack could not be found
+    Unavailable {
+        pack_id: String,
+        required_by: Option<String>,
+    },
+}
+
+/// A pack that uses a different version than the one that was asked for
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VersionSubstitution {
+    pub pack_id: String,
+    pub requested: String,
+    pub selected: String,
+}
+
+impl Display for DependencyConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let required_by = |required_by: &Option<String>| match required_by {
+            Some(pack_id) => format!(" (required by '{pack_id}')"),
+            None => String::new(),
+        };
+        match self {
+            Self::VersionMismatch {
+                pack_id,
+                selected,
+                required,
+                required_by: by,
+            } => write!(
+                f,
+                "'{pack_id}' is at version {selected} but version {required} is needed{}",
+                required_by(by)
+            ),
+            Self::NoCompatibleVersion {
+                pack_id,
+                required_by: by,
+            } => write!(
+                f,
+                "'{pack_id}' has no version for this Minecraft version{}",
+                required_by(by)
+            ),
+            Self::Unavailable {
+                pack_id,
+                required_by: by,
+            } => write!(f, "'{pack_id}' could not be found{}", required_by(by)),
+        }
+    }
+}
+
+/// Resolve the full set of packs needed to add a pack to a bundle, fetching pack data as needed
+pub async fn resolve_dependencies<F, Fut>(
+    pack: PackReference,
+    minecraft_version: &str,
+    existing: &[PackReference],
+    mut fetch: F,
+) -> anyhow::Result<DependencyResolution>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<PackData>>,
+{
+    // The requested pack has to exist, but missing dependencies are only reported
+    let root = fetch(pack.id.clone()).await?;
+    let mut packs = HashMap::new();
+    let mut queue = dependencies_of(&root, &pack.version, minecraft_version);
+    packs.insert(pack.id.clone(), Some(root));
+    while let Some(dep) = queue.pop() {
+        if packs.contains_key(&dep.id) {
+            continue;
+        }
+        let data = fetch(dep.id.clone()).await.ok();
+        if let Some(data) = &data {
+            queue.extend(dependencies_of(data, &dep.version, minecraft_version));
+        }
+        packs.insert(dep.id, data);
+    }
+
+    Ok(resolve(pack, minecraft_version, existing, &packs))
+}
+
+/// Resolve the dependencies of a pack using pack data that has already been fetched.
+/// Packs that could not be fetched are stored as None.
+pub fn resolve(
+    pack: PackReference,
+    minecraft_version: &str,
+    existing: &[PackReference],
+    packs: &HashMap<String, Option<PackData>>,
+) -> DependencyResolution {
+    let mut resolver = Resolver {
+        minecraft_version,
+        existing,
+        packs,
+        selected: HashMap::new(),
+        stack: Vec::new(),
+        out: DependencyResolution::default(),
+    };
+    resolver.visit(&pack, None);
+
+    resolver.out
+}
+
+/// State for walking the dependency graph
+struct Resolver<'a> {
+    minecraft_version: &'a str,
+    existing: &'a [PackReference],
+    packs: &'a HashMap<String, Option<PackData>>,
+    /// The versions that have been selected so far
+    selected: HashMap<String, String>,
+    /// The packs that are currently being visited
+    stack: Vec<String>,
+    out: DependencyResolution,
+}
+
+impl<'a> Resolver<'a> {
+    fn visit(&mut self, pack: &PackReference, required_by: Option<&str>) {
+        let required_by_owned = || required_by.map(str::to_string);
+
+        if let Some(pos) = self.stack.iter().position(|id| id == &pack.id) {
+            let mut cycle = self.stack[pos..].to_vec();
+            cycle.push(pack.id.clone());
+            self.out.cycles.push(cycle);
+            return;
+        }
+
+        let Some(Some(data)) = self.packs.get(&pack.id) else {
+            self.out.conflicts.push(DependencyConflict::Unavailable {
+                pack_id: pack.id.clone(),
+                required_by: required_by_owned(),
+            });
+            return;
+        };
+        let Some(version) = select_version(data, &pack.version, self.minecraft_version) else {
+            self.out
+                .conflicts
+                .push(DependencyConflict::NoCompatibleVersion {
+                    pack_id: pack.id.clone(),
+                    required_by: required_by_owned(),
+                });
+            return;
+        };
+
+        // Packs that are already in the bundle or already selected aren't added again
+        let current = self
+            .existing
+            .iter()
+            .find(|existing| existing.id == pack.id)
+            .map(|existing| &existing.version)
+            .or_else(|| self.selected.get(&pack.id));
+        if let Some(current) = current {
+            if current != &version.name {
+                self.out
+                    .conflicts
+                    .push(DependencyConflict::VersionMismatch {
+                        pack_id: pack.id.clone(),
+                        selected: current.clone(),
+                        required: version.name.clone(),
+                        required_by: required_by_owned(),
+                    });
+            }
+            return;
+        }
+
+        if version.name != pack.version {
+            self.out.substituted.push(VersionSubstitution {
+                pack_id: pack.id.clone(),
+                requested: pack.version.clone(),
+                selected: version.name.clone(),
+            });
+        }
+        self.selected.insert(pack.id.clone(), version.name.clone());
+        self.stack.push(pack.id.clone());
+        for dep in version.dependencies.iter().flatten() {
+            self.visit(dep, Some(&pack.id));
+        }
+        self.stack.pop();
+
+        self.out.added.push(PackReference {
+            id: pack.id.clone(),
+            version: version.name.clone(),
+        });
+    }
+}
+
+/// Select the version of a pack to use. The requested version is used if it supports the
+/// Minecraft version, otherwise the newest version that does is used instead.
+pub fn select_version<'a>(
+    pack: &'a PackData,
+    requested: &str,
+    minecraft_version: &str,
+) -> Option<&'a PackVersion> {
+    pack.get_version(requested)
+        .filter(|version| version.supports.iter().any(|x| x == minecraft_version))
+        .or_else(|| pack.get_newest_version(minecraft_version))
+}
+
+fn dependencies_of(
+    pack: &PackData,
+    requested: &str,
+    minecraft_version: &str,
+) -> Vec<PackReference> {
+    select_version(pack, requested, minecraft_version)
+        .and_then(|version| version.dependencies.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api_types::{PackDisplay, PackVersionDownloads};
+
+    use super::*;
+
+    fn pack_ref(id: &str, version: &str) -> PackReference {
+        PackReference {
+            id: id.into(),
+            version: version.into(),
+        }
+    }
+
+    fn pack(id: &str, versions: &[(&str, &str, &[PackReference])]) -> (String, Option<PackData>) {
+        let versions = versions
+            .iter()
+            .map(|(name, supports, deps)| PackVersion {
+                name: name.to_string(),
+                downloads: PackVersionDownloads {
+                    datapack: None,
+                    resource_pack: None,
+                },
+                supports: vec![supports.to_string()],
+                dependencies: Some(deps.to_vec()),
+                hashes: None,
+            })
+            .collect();
+        let data = PackData {
+            id: id.into(),
+            versions,
+            display: PackDisplay {
+                name: id.into(),
+                description: String::new(),
+                icon: String::new(),
+                hidden: false,
+                web_page: None,
+                urls: None,
+            },
+        };
+        (id.into(), Some(data))
+    }
+
+    #[test]
+    fn test_resolve_closure() {
+        let packs = HashMap::from([
+            pack("app", &[("1", "1.20.1", &[pack_ref("lib", "1")])]),
+            pack(
+                "lib",
+                &[
+                    ("1", "1.20", &[]),
+                    ("2", "1.20.1", &[pack_ref("core", "1")]),
+                ],
+            ),
+            pack("core", &[("1", "1.20.1", &[])]),
+        ]);
+
+        let out = resolve(pack_ref("app", "1"), "1.20.1", &[], &packs);
+        assert_eq!(
+            out.added,
+            vec![
+                pack_ref("core", "1"),
+                pack_ref("lib", "2"),
+                pack_ref("app", "1")
+            ]
+        );
+        assert!(out.conflicts.is_empty());
+        assert!(out.cycles.is_empty());
+        // lib 1 doesn't support 1.20.1, so the newest version that does is used
+        assert_eq!(
+            out.substituted,
+            vec![VersionSubstitution {
+                pack_id: "lib".into(),
+                requested: "1".into(),
+                selected: "2".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_conflicts_and_cycles() {
+        let packs = HashMap::from([
+            pack(
+                "a",
+                &[("1", "1.20.1", &[pack_ref("b", "1"), pack_ref("lib", "1")])],
+            ),
+            pack("b", &[("1", "1.20.1", &[pack_ref("a", "1")])]),
+            pack("lib", &[("1", "1.20.1", &[]), ("2", "1.20.1", &[])]),
+            ("missing".into(), None),
+        ]);
+
+        let existing = [pack_ref("lib", "2")];
+        let out = resolve(pack_ref("a", "1"), "1.20.1", &existing, &packs);
+        assert_eq!(out.added, vec![pack_ref("b", "1"), pack_ref("a", "1")]);
+        assert_eq!(
+            out.cycles,
+            vec![vec!["a".to_string(), "b".into(), "a".into()]]
+        );
+        assert_eq!(
+            out.conflicts,
+            vec![DependencyConflict::VersionMismatch {
+                pack_id: "lib".into(),
+                selected: "2".into(),
+                required: "1".into(),
+                required_by: Some("a".into()),
+            }]
+        );
+
+        let out = resolve(pack_ref("missing", "1"), "1.20.1", &[], &packs);
+        assert_eq!(out.added, vec![]);
+        assert_eq!(out.conflicts.len(), 1);
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+/// Write a file so that it is either fully written or left as it was, even if the
+/// launcher crashes or the power goes out while it is being written.
+/// The previous version of the file is kept as a backup.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    if path.exists() {
+        let backup = backup_path(path);
+        let backup_tmp = tmp_path(&backup);
+        std::fs::copy(path, &backup_tmp).context("Failed to copy file to backup")?;
+        File::open(&backup_tmp)
+            .and_then(|file| file.sync_all())
+            .context("Failed to sync backup")?;
+        std::fs::rename(&backup_tmp, &backup).context("Failed to replace backup")?;
+    }
+
+    write_replace(path, contents)
+}
+
+/// Write a file through a temporary file, without changing the backup
+pub fn write_replace(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    replace_with(path, contents, &OpenOptions::new())
+}
+
+/// Write a file like `write_replace` that is only readable and writable by the current user.
+/// The temporary file is created with those permissions, so the contents are never readable
+/// by anyone else, even briefly.
+pub fn write_private(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    // A temporary file left by a crash would keep its old permissions if it was reused
+    let _ = std::fs::remove_file(tmp_path(path));
+    replace_with(path, contents, &options)?;
+    restrict_permissions(path)
+}
+
+fn replace_with(path: &Path, contents: &[u8], options: &OpenOptions) -> anyhow::Result<()> {
+    let tmp = tmp_path(path);
+    let mut file = options
+        .clone()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp)
+        .context("Failed to create temporary file")?;
+    file.write_all(contents)
+        .context("Failed to write temporary file")?;
+    file.sync_all().context("Failed to sync temporary file")?;
+    drop(file);
+    std::fs::rename(&tmp, path).context("Failed to replace file")?;
+    sync_parent(path);
+
+    Ok(())
+}
+
+/// The path to the backup of a file written with `write_atomic`
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".tmp")
+}
+
+/// Add a suffix to the filename of a path
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut filename = path.file_name().unwrap_or_default().to_os_string();
+    filename.push(suffix);
+    path.with_file_name(filename)
+}
+
+/// Make sure that a rename in a directory is persisted. This is only possible on Unix.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Make a file only readable and writable by the current user.
+/// On Windows the permissions are left as they are, so the file is only as private as the
+/// directory it is in. The launcher's directories are in the user's profile, which other
+/// users can't read by default, but administrators still can.
+pub fn restrict_permissions(path: &Path) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        write_atomic(&path, b"first").unwrap();
+        assert!(!backup_path(&path).exists());
+
+        write_atomic(&path, b"second").unwrap();
+        write_atomic(&path, b"third").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"third");
+        assert_eq!(std::fs::read(backup_path(&path)).unwrap(), b"second");
+        assert!(!tmp_path(&path).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.json");
+        std::fs::write(tmp_path(&path), b"leftover").unwrap();
+        write_private(&path, b"secret").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"secret");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!tmp_path(&path).exists());
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
+
+/// A hash that a downloaded file is expected to have
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "algorithm", content = "hash", rename_all = "snake_case")]
+pub enum ExpectedHash {
+    Sha1(String),
+    Sha512(String),
+}
+
+impl ExpectedHash {
+    /// Pick the strongest of the available hashes
+    pub fn best(sha1: Option<&String>, sha512: Option<&String>) -> Option<Self> {
+        sha512
+            .map(|hash| Self::Sha512(hash.clone()))
+            .or_else(|| sha1.map(|hash| Self::Sha1(hash.clone())))
+    }
+
+    /// Get the name of the hash algorithm
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            Self::Sha1(..) => "SHA-1",
+            Self::Sha512(..) => "SHA-512",
+        }
+    }
+
+    /// Get the expected hash as a hex string
+    pub fn hash(&self) -> &str {
+        match self {
+            Self::Sha1(hash) | Self::Sha512(hash) => hash,
+        }
+    }
+
+    /// Compute the hash of some data with the same algorithm as this hash
+    pub fn compute(&self, data: &[u8]) -> String {
+        match self {
+            Self::Sha1(..) => sha1(data),
+            Self::Sha512(..) => hex::encode(Sha512::digest(data)),
+        }
+    }
+
+    /// Check that data matches this hash
+    pub fn verify(&self, data: &[u8], artifact: &str) -> Result<(), HashMismatchError> {
+        let actual = self.compute(data);
+        if actual.eq_ignore_ascii_case(self.hash()) {
+            Ok(())
+        } else {
+            Err(HashMismatchError {
+                artifact: artifact.to_string(),
+                algorithm: self.algorithm(),
+                expected: self.hash().to_string(),
+                actual,
+            })
+        }
+    }
+
+    /// Check whether a file exists and matches this hash
+    pub fn matches_file(&self, path: &Path) -> bool {
+        match std::fs::read(path) {
+            Ok(data) => self.compute(&data).eq_ignore_ascii_case(self.hash()),
+            Err(..) => false,
+        }
+    }
+}
+
+/// Compute the SHA-1 hash of some data as a hex string
+pub fn sha1(data: &[u8]) -> String {
+    hex::encode(Sha1::digest(data))
+}
+
+/// Error when a downloaded file does not have the hash that it should
+#[derive(Debug, Clone)]
+pub struct HashMismatchError {
+    /// The name of the file that was downloaded
+    pub artifact: String,
+    pub algorithm: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Display for HashMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hash mismatch for {}: expected {}, got {}",
+            self.algorithm, self.artifact, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for HashMismatchError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let hash = ExpectedHash::Sha1("A9993E364706816ABA3E25717850C26C9CD0D89D".into());
+        assert!(hash.verify(b"abc", "abc.txt").is_ok());
+
+        let err = hash.verify(b"abd", "abc.txt").unwrap_err();
+        assert_eq!(err.artifact, "abc.txt");
+        assert_eq!(err.algorithm, "SHA-1");
+
+        let hash = ExpectedHash::Sha512(sha512_of_abc());
+        assert!(hash.verify(b"abc", "abc.txt").is_ok());
+    }
+
+    fn sha512_of_abc() -> String {
+        concat!(
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a",
+            "2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        )
+        .into()
+    }
+}
@@ -0,0 +1,288 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Create the HTTP client used for all requests
+pub fn create_client() -> anyhow::Result<Client> {
+    Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+/// How failed requests are retried
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// How many times a request is made before giving up
+    pub max_attempts: u32,
+    /// How long to wait before the first retry, in milliseconds. This doubles for every retry.
+    pub initial_backoff_ms: u64,
+    /// The longest to wait between retries, in milliseconds
+    pub max_backoff_ms: u64,
+}
+
+impl RetryPolicy {
+    /// How long to wait before retrying after a failed attempt, starting at 1
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff_ms
+            .saturating_mul(2u64.saturating_pow(attempt.saturating_sub(1)));
+        Duration::from_millis(backoff.min(self.max_backoff_ms))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 8000,
+        }
+    }
+}
+
+/// HTTP client that retries GET requests that fail for reasons that might go away
+#[derive(Clone)]
+pub struct HttpClient {
+    pub client: Client,
+    pub retry: RetryPolicy,
+}
+
+impl HttpClient {
+    pub fn new(client: Client, retry: RetryPolicy) -> Self {
+        Self { client, retry }
+    }
+
+    /// Get JSON from a URL
+    pub async fn get_json<T: DeserializeOwned>(&self, url: &str) -> anyhow::Result<T> {
+        let out = self
+            .get_with(url, HeaderMap::new(), |response| async move {
+                response.error_for_status()?.json().await
+            })
+            .await?;
+        Ok(out)
+    }
+
+    /// Download the bytes of a URL, reporting the progress under a name as they come in
+    pub async fn download(
+        &self,
+        url: &str,
+        name: &str,
+        progress: &impl DownloadProgress,
+    ) -> anyhow::Result<Vec<u8>> {
+        let out = self
+            .get_with(url, HeaderMap::new(), |response| async move {
+                let mut response = response.error_for_status()?;
+                let total = response.content_length();
+                let mut bytes = Vec::new();
+                progress.report(name, 0, total);
+                let mut last_report = Instant::now();
+                while let Some(chunk) = response.chunk().await? {
+                    bytes.extend_from_slice(&chunk);
+                    if last_report.elapsed() >= PROGRESS_INTERVAL {
+                        progress.report(name, bytes.len() as u64, total);
+                        last_report = Instant::now();
+                    }
+                }
+                progress.report(name, bytes.len() as u64, total);
+                Ok(bytes)
+            })
+            .await?;
+        Ok(out)
+    }
+
+    /// Make a GET request and read the response. The request is retried if it fails to
+    /// connect, times out, or gets a 429 or 5xx status, waiting for as long as the
+    /// Retry-After header asks if there is one. Reading the response is retried as well.
+    pub async fn get_with<T, F, Fut>(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+        read: F,
+    ) -> reqwest::Result<T>
+    where
+        F: Fn(Response) -> Fut,
+        Fut: Future<Output = reqwest::Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let can_retry = attempt < self.retry.max_attempts;
+            let result = self.client.get(url).headers(headers.clone()).send().await;
+            let delay = match result {
+                Ok(response) if can_retry && is_retryable_status(response.status()) => {
+                    retry_after(&response).unwrap_or_else(|| self.retry.backoff(attempt))
+                }
+                Ok(response) => match read(response).await {
+                    Err(e) if can_retry && is_transient(&e) => self.retry.backoff(attempt),
+                    result => return result,
+                },
+                Err(e) if can_retry && is_transient(&e) => self.retry.backoff(attempt),
+                Err(e) => return Err(e),
+            };
+            eprintln!("Request to {url} failed, retrying in {delay:?}");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Receives the progress of downloads
+pub trait DownloadProgress: Sync {
+    /// Report how many bytes of a download are done. The total is unknown if the
+    /// server didn't send a length.
+    fn report(&self, name: &str, current: u64, total: Option<u64>);
+}
+
+/// Don't report progress
+impl DownloadProgress for () {
+    fn report(&self, _name: &str, _current: u64, _total: Option<u64>) {}
+}
+
+impl<F: Fn(&str, u64, Option<u64>) + Sync> DownloadProgress for F {
+    fn report(&self, name: &str, current: u64, total: Option<u64>) {
+        self(name, current, total)
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Whether an error from sending a request or reading the response might go away if it is retried
+fn is_transient(err: &reqwest::Error) -> bool {
+    err.status().is_none() && (err.is_timeout() || err.is_connect() || err.is_body())
+}
+
+/// Get how long the server asked to wait before retrying
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, chrono::Utc::now()).map(|delay| delay.min(MAX_RETRY_AFTER))
+}
+
+/// Parse a Retry-After value, which is either a number of seconds or a date
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&chrono::Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// How long to wait to connect to a server
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait for more data from a server
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// The longest to wait when a server asks to retry later
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// How often to report the progress of a download
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use crate::test_server::{StubResponse, StubServer};
+
+    fn client() -> HttpClient {
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 10,
+        };
+        HttpClient::new(create_client().unwrap(), retry)
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let server = StubServer::start();
+        let client = client();
+        let url = |path: &str| format!("{}{path}", server.url());
+        server
+            .route("/flaky", StubResponse::status(503))
+            .route(
+                "/flaky",
+                StubResponse::status(429).header("Retry-After", "0"),
+            )
+            .route("/flaky", StubResponse::bytes(b"ok".to_vec()));
+        assert_eq!(
+            client.download(&url("/flaky"), "flaky", &()).await.unwrap(),
+            b"ok"
+        );
+        assert_eq!(server.requests("/flaky").len(), 3);
+
+        server.route("/down", StubResponse::status(500));
+        assert!(client.download(&url("/down"), "down", &()).await.is_err());
+        assert_eq!(server.requests("/down").len(), 3);
+
+        // Client errors are not retried
+        assert!(client
+            .download(&url("/missing"), "missing", &())
+            .await
+            .is_err());
+        assert_eq!(server.requests("/missing").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_download_progress() {
+        let server = StubServer::start();
+        server.route("/pack.zip", StubResponse::bytes(vec![7; 4096]));
+        let reports = Mutex::new(Vec::new());
+        let progress = |name: &str, current, total| {
+            reports
+                .lock()
+                .unwrap()
+                .push((name.to_string(), current, total));
+        };
+        let url = format!("{}/pack.zip", server.url());
+        let bytes = client().download(&url, "pack", &progress).await.unwrap();
+        assert_eq!(bytes.len(), 4096);
+
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.first(), Some(&("pack".to_string(), 0, Some(4096))));
+        assert_eq!(
+            reports.last(),
+            Some(&("pack".to_string(), 4096, Some(4096)))
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = chrono::DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(3), Duration::from_millis(2000));
+        assert_eq!(policy.backoff(40), Duration::from_millis(8000));
+    }
+}
@@ -4,11 +4,24 @@
 )]
 
 pub mod api;
+pub mod api_cache;
 pub mod api_types;
 pub mod config;
+pub mod dependencies;
+pub mod files;
+pub mod hashes;
+pub mod http;
 pub mod mcvm;
 pub mod minecraft;
+pub mod pack_cache;
+pub mod paths;
+pub mod secrets;
 pub mod tauri_utils;
+/// HTTP server for testing requests to the API without the real service
+#[cfg(test)]
+mod test_server;
+pub mod updates;
+pub mod weld;
 
 use tauri_utils::SmithedState;
 
@@ -21,6 +34,9 @@ fn main() -> anyhow::Result<()> {
             /* REGISTER TAURI IPC COMMANDS */
             commands::launch_game,
             commands::stop_game,
+            commands::get_game_status,
+            commands::get_game_statuses,
+            commands::clear_game_status,
             commands::add_bundle,
             commands::get_bundle,
             commands::list_bundles,
@@ -30,8 +46,15 @@ fn main() -> anyhow::Result<()> {
             commands::remove_pack_from_bundle,
             commands::get_pack_version_for_bundle,
             commands::get_bundle_packs,
+            commands::check_bundle_updates,
+            commands::update_bundle_packs,
             commands::get_remote_bundle,
             commands::import_bundle,
+            commands::clear_pack_cache,
+            commands::list_accounts,
+            commands::add_account,
+            commands::remove_account,
+            commands::select_account,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
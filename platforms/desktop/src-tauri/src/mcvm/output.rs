@@ -2,18 +2,88 @@ use mcvm::shared::output::{MCVMOutput, Message, MessageContents, MessageLevel};
 use serde::Serialize;
 use tauri::{AppHandle, Manager};
 
+use crate::http::DownloadProgress;
+use crate::tauri_utils::status::{GameEvent, GameStatus, STATUS_CHANGED_EVENT};
+use crate::tauri_utils::SmithedState;
+
 pub struct SmithedMCVMOutput {
     app: AppHandle,
+    /// The bundle that is being launched, if any
+    bundle_id: Option<String>,
 }
 
 impl SmithedMCVMOutput {
     pub fn new(app: AppHandle) -> Self {
-        Self { app }
+        Self {
+            app,
+            bundle_id: None,
+        }
+    }
+
+    /// Create output for launching a bundle
+    pub fn for_bundle(app: AppHandle, bundle_id: String) -> Self {
+        Self {
+            app,
+            bundle_id: Some(bundle_id),
+        }
     }
 
     pub fn get_app_handle(self) -> AppHandle {
         self.app
     }
+
+    pub fn app_handle(&self) -> &AppHandle {
+        &self.app
+    }
+
+    /// Update the game status and notify the frontend
+    pub fn set_status(&self, status: GameStatus) {
+        if let Some(bundle_id) = &self.bundle_id {
+            set_game_status(&self.app, bundle_id, status);
+        }
+    }
+
+    /// Send an output event. When launching a bundle, it is scoped to the bundle so that
+    /// the output of games that are launched at the same time doesn't get mixed together.
+    fn emit_output<T: Serialize + Clone>(&self, name: &str, event: T) {
+        if self.bundle_id.is_some() {
+            self.emit_game_event(name, event);
+        } else {
+            let _ = self.app.emit_all(name, event);
+        }
+    }
+
+    /// Send an event for the game of the bundle that is being launched
+    pub fn emit_game_event<T: Serialize + Clone>(&self, name: &str, event: T) {
+        if let Some(bundle_id) = &self.bundle_id {
+            let _ = self.app.emit_all(
+                name,
+                GameEvent {
+                    bundle_id: bundle_id.clone(),
+                    event,
+                },
+            );
+        }
+    }
+}
+
+/// Update the status of a bundle's game in the shared state and notify the frontend
+pub fn set_game_status(app: &AppHandle, bundle_id: &str, status: GameStatus) {
+    let state = app.state::<SmithedState>();
+    if let Ok(mut lock) = state.game_statuses.lock() {
+        if status == GameStatus::Idle {
+            lock.remove(bundle_id);
+        } else {
+            lock.insert(bundle_id.to_string(), status.clone());
+        }
+    }
+    let _ = app.emit_all(
+        STATUS_CHANGED_EVENT,
+        GameEvent {
+            bundle_id: bundle_id.to_string(),
+            event: status,
+        },
+    );
 }
 
 impl MCVMOutput for SmithedMCVMOutput {
@@ -28,12 +98,13 @@ impl MCVMOutput for SmithedMCVMOutput {
         match message.contents {
             MessageContents::Associated(assoc, msg) => match *assoc {
                 MessageContents::Progress { current, total } => {
-                    let _ = self.app.emit_all(
+                    self.emit_output(
                         "mcvm_output_progress",
                         AssociatedProgressEvent {
-                            current,
-                            total,
+                            current: current.into(),
+                            total: Some(total.into()),
                             message: msg.default_format(),
+                            unit: ProgressUnit::Steps,
                         },
                     );
                 }
@@ -43,8 +114,8 @@ impl MCVMOutput for SmithedMCVMOutput {
                     msg.default_format()
                 )),
             },
-            MessageContents::Header(text) => {
-                let _ = self.app.emit_all("mcvm_output_header", MessageEvent(text));
+            MessageContents::Header(message) => {
+                self.emit_output("mcvm_output_header", MessageEvent { message });
             }
             msg => self.disp(msg.default_format()),
         }
@@ -52,7 +123,7 @@ impl MCVMOutput for SmithedMCVMOutput {
 
     fn display_special_ms_auth(&mut self, url: &str, code: &str) {
         self.display_text("Showing auth info".into(), MessageLevel::Important);
-        let _ = self.app.emit_all(
+        self.emit_output(
             "mcvm_display_auth_info",
             AuthDisplayEvent {
                 url: url.to_owned(),
@@ -62,23 +133,49 @@ impl MCVMOutput for SmithedMCVMOutput {
     }
 }
 
+impl DownloadProgress for SmithedMCVMOutput {
+    fn report(&self, name: &str, current: u64, total: Option<u64>) {
+        self.emit_output(
+            "mcvm_output_progress",
+            AssociatedProgressEvent {
+                current,
+                total,
+                message: format!("Downloading {name}"),
+                unit: ProgressUnit::Bytes,
+            },
+        );
+    }
+}
+
 impl SmithedMCVMOutput {
-    fn disp(&mut self, text: String) {
-        println!("{text}");
-        let _ = self.app.emit_all("mcvm_output_message", MessageEvent(text));
+    fn disp(&mut self, message: String) {
+        println!("{message}");
+        self.emit_output("mcvm_output_message", MessageEvent { message });
     }
 }
 
 /// Event for a message
 #[derive(Clone, Serialize)]
-pub struct MessageEvent(String);
+pub struct MessageEvent {
+    message: String,
+}
 
 /// Event for an associated progressbar
 #[derive(Clone, Serialize)]
 pub struct AssociatedProgressEvent {
-    pub current: u32,
-    pub total: u32,
+    pub current: u64,
+    /// Unknown when a download has no length
+    pub total: Option<u64>,
     pub message: String,
+    pub unit: ProgressUnit,
+}
+
+/// What the progress of an event counts
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressUnit {
+    Steps,
+    Bytes,
 }
 
 /// Event for the auth display
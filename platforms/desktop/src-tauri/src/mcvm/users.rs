@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use directories::ProjectDirs;
+use mcvm::core::auth_crate::mc::AccessToken;
+use mcvm::core::user::{User, UserKind};
+use serde::{Deserialize, Serialize};
+
+use crate::files;
+use crate::minecraft::auth::microsoft::{AccountTokens, MicrosoftLogin};
+use crate::paths;
+use crate::secrets::{EncryptedSecret, SecretKey};
+
+/// The Microsoft accounts that the user has logged in with
+#[derive(Serialize, Deserialize, Default)]
+pub struct UserStore {
+    /// The ID of the account to launch the game with
+    #[serde(default)]
+    pub selected_user: Option<String>,
+    #[serde(default)]
+    pub users: HashMap<String, UserStored>,
+}
+
+/// A stored Microsoft account
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UserStored {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    /// The account tokens, encrypted with the key in the key file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<EncryptedSecret>,
+    /// Plaintext access token written by old versions. It is only read so that it can be encrypted.
+    #[serde(default, skip_serializing)]
+    access_token: Option<String>,
+}
+
+/// Account info for the frontend
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccountInfo {
+    pub id: String,
+    pub name: String,
+    pub uuid: Option<String>,
+    pub selected: bool,
+}
+
+/// The users file, which old versions wrote as a plain map of users
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UsersFile {
+    Legacy(HashMap<String, UserStored>),
+    Current(UserStore),
+}
+
+impl UserStore {
+    /// Opens the user store, or creates an empty one if it doesn't exist.
+    /// Users files from old versions are migrated and rewritten with their tokens encrypted.
+    pub fn open(dirs: &ProjectDirs) -> anyhow::Result<Self> {
+        Self::open_path(&Self::path(dirs), || Self::open_key(dirs))
+    }
+
+    /// Open the user store at a path. The key is only opened if there are tokens to encrypt.
+    fn open_path(
+        path: &Path,
+        open_key: impl FnOnce() -> anyhow::Result<SecretKey>,
+    ) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path).context("Failed to read users file")?;
+        let file = serde_json::from_str(&text).context("Failed to parse users file")?;
+        let (mut store, mut migrated) = match file {
+            UsersFile::Current(store) => (store, false),
+            UsersFile::Legacy(users) => {
+                let users = users
+                    .into_values()
+                    .map(|user| (user.id.clone(), user))
+                    .collect();
+                let store = Self {
+                    selected_user: None,
+                    users,
+                };
+                (store, true)
+            }
+        };
+        let legacy_tokens: Vec<_> = store
+            .users
+            .values_mut()
+            .filter_map(|user| Some((user.id.clone(), user.access_token.take()?)))
+            .collect();
+        if !legacy_tokens.is_empty() {
+            // Old versions didn't store when the token expires or a refresh token, so the token
+            // is kept until it would have expired and then the account has to log in again
+            let expires_at = legacy_expiry(path);
+            let key = open_key().context("Failed to open key to encrypt migrated tokens")?;
+            for (id, access_token) in legacy_tokens {
+                let tokens = AccountTokens {
+                    access_token,
+                    expires_at,
+                    refresh_token: None,
+                };
+                store.set_tokens(&id, &tokens, &key)?;
+            }
+            migrated = true;
+        }
+        if migrated {
+            store
+                .write_path(path)
+                .context("Failed to rewrite migrated users file")?;
+        }
+
+        Ok(store)
+    }
+
+    /// Writes the user store, replacing the old file only once the new one is written
+    pub fn write(&self, dirs: &ProjectDirs) -> anyhow::Result<()> {
+        self.write_path(&Self::path(dirs))
+    }
+
+    fn write_path(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+        }
+        let text = serde_json::to_string_pretty(self).context("Failed to serialize users")?;
+        files::write_private(path, text.as_bytes()).context("Failed to write users file")
+    }
+
+    /// Open the key used to encrypt account tokens
+    pub fn open_key(dirs: &ProjectDirs) -> anyhow::Result<SecretKey> {
+        SecretKey::load_or_create(&paths::token_key_file(dirs))
+    }
+
+    /// Store an account that was logged in to, selecting it if no account is selected yet.
+    /// Logging in to an existing account replaces its tokens.
+    pub fn add(&mut self, login: &MicrosoftLogin, key: &SecretKey) -> anyhow::Result<AccountInfo> {
+        let stored = UserStored {
+            id: login.profile.id.clone(),
+            name: login.profile.name.clone(),
+            uuid: Some(login.profile.id.clone()),
+            tokens: Some(key.encrypt_json(&login.tokens)?),
+            access_token: None,
+        };
+        if self.selected_user.is_none() {
+            self.selected_user = Some(stored.id.clone());
+        }
+        let info = self.info(&stored);
+        self.users.insert(stored.id.clone(), stored);
+
+        Ok(info)
+    }
+
+    /// Decrypt the tokens of an account
+    pub fn get_tokens(&self, id: &str, key: &SecretKey) -> anyhow::Result<Option<AccountTokens>> {
+        let Some(tokens) = self.users.get(id).and_then(|user| user.tokens.as_ref()) else {
+            return Ok(None);
+        };
+        key.decrypt_json(tokens)
+            .context("Failed to decrypt account tokens")
+            .map(Some)
+    }
+
+    /// Replace the tokens of an account
+    pub fn set_tokens(
+        &mut self,
+        id: &str,
+        tokens: &AccountTokens,
+        key: &SecretKey,
+    ) -> anyhow::Result<()> {
+        if let Some(user) = self.users.get_mut(id) {
+            user.tokens = Some(key.encrypt_json(tokens)?);
+        }
+
+        Ok(())
+    }
+
+    /// Remove an account, selecting another one if it was selected.
+    /// Returns false if the account does not exist.
+    pub fn remove(&mut self, id: &str) -> bool {
+        if self.users.remove(id).is_none() {
+            return false;
+        }
+        if self.selected_user.as_deref() == Some(id) {
+            self.selected_user = self.users.keys().min().cloned();
+        }
+
+        true
+    }
+
+    /// Select the account to launch the game with. Returns false if the account does not exist.
+    pub fn select(&mut self, id: &str) -> bool {
+        if !self.users.contains_key(id) {
+            return false;
+        }
+        self.selected_user = Some(id.to_string());
+
+        true
+    }
+
+    /// Get the selected account
+    pub fn get_selected(&self) -> Option<&UserStored> {
+        self.users.get(self.selected_user.as_ref()?)
+    }
+
+    /// List all of the accounts, sorted by name
+    pub fn list(&self) -> Vec<AccountInfo> {
+        let mut out: Vec<_> = self.users.values().map(|user| self.info(user)).collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        out
+    }
+
+    fn info(&self, user: &UserStored) -> AccountInfo {
+        AccountInfo {
+            id: user.id.clone(),
+            name: user.name.clone(),
+            uuid: user.uuid.clone(),
+            selected: self.selected_user.as_ref() == Some(&user.id),
+        }
+    }
+
+    fn path(dirs: &ProjectDirs) -> PathBuf {
+        paths::users_file(dirs)
+    }
+}
+
+/// When a plaintext token from an old version expires. The token was written when the account
+/// logged in, so it is estimated from when the users file was last modified.
+fn legacy_expiry(path: &Path) -> i64 {
+    let modified = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok());
+    match modified {
+        Some(modified) => modified.as_secs() as i64 + LEGACY_TOKEN_LIFETIME,
+        None => 0,
+    }
+}
+
+/// How long Minecraft access tokens are valid for, in seconds
+const LEGACY_TOKEN_LIFETIME: i64 = 24 * 60 * 60;
+
+impl UserStored {
+    /// Create the mcvm user for this account. Offline users don't need tokens.
+    pub fn to_user(&self, offline: bool, tokens: Option<&AccountTokens>) -> User {
+        let kind = if offline {
+            UserKind::Unverified
+        } else {
+            UserKind::Microsoft { xbox_uid: None }
+        };
+        let mut user = User::new(kind, &self.id, &self.name);
+        if let Some(uuid) = &self.uuid {
+            user.set_uuid(uuid);
+        }
+        if let Some(tokens) = tokens {
+            user.set_access_token(AccessToken(tokens.access_token.clone()));
+        }
+
+        user
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcvm::MinecraftProfile;
+
+    #[test]
+    fn test_migrate_legacy_users() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.json");
+        let legacy = serde_json::json!({
+            "steve": {
+                "id": "abc",
+                "name": "Steve",
+                "uuid": "abc",
+                "access_token": "plaintext",
+            }
+        });
+        std::fs::write(&path, legacy.to_string()).unwrap();
+        let key_path = dir.path().join("key");
+        let open_key = || SecretKey::load_or_create(&key_path);
+
+        let store = UserStore::open_path(&path, open_key).unwrap();
+        assert_eq!(store.selected_user, None);
+        let user = &store.users["abc"];
+        assert_eq!(user.name, "Steve");
+
+        // The token is kept, encrypted, until it would have expired
+        let key = open_key().unwrap();
+        let tokens = store.get_tokens("abc", &key).unwrap().unwrap();
+        assert_eq!(tokens.access_token, "plaintext");
+        assert!(tokens.refresh_token.is_none());
+        assert!(!tokens.is_expired());
+
+        // The file is rewritten in the current format without the plaintext token
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("access_token"));
+        assert!(!text.contains("plaintext"));
+        let rewritten: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(rewritten["users"]["abc"]["name"], "Steve");
+        let store = UserStore::open_path(&path, open_key).unwrap();
+        assert_eq!(store.users.len(), 1);
+        assert_eq!(
+            store.get_tokens("abc", &key).unwrap().unwrap().access_token,
+            "plaintext"
+        );
+    }
+
+    #[test]
+    fn test_open_without_legacy_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.json");
+        std::fs::write(&path, r#"{"users": {}}"#).unwrap();
+
+        // The key isn't needed when there is nothing to encrypt
+        let store = UserStore::open_path(&path, || panic!("Key was opened")).unwrap();
+        assert!(store.users.is_empty());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.json");
+        let key = SecretKey::load_or_create(&dir.path().join("key")).unwrap();
+        let login = MicrosoftLogin {
+            profile: MinecraftProfile {
+                id: "abc".into(),
+                name: "Steve".into(),
+            },
+            tokens: AccountTokens {
+                access_token: "access".into(),
+                expires_at: 100,
+                refresh_token: Some("refresh".into()),
+            },
+        };
+
+        let mut store = UserStore::default();
+        let info = store.add(&login, &key).unwrap();
+        assert!(info.selected);
+        store.write_path(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("access_token"));
+        assert!(!text.contains("refresh"));
+
+        let store =
+            UserStore::open_path(&path, || SecretKey::load_or_create(&dir.path().join("key")))
+                .unwrap();
+        assert_eq!(store.selected_user.as_deref(), Some("abc"));
+        assert_eq!(store.list(), vec![info]);
+        let tokens = store.get_tokens("abc", &key).unwrap().unwrap();
+        assert_eq!(tokens.access_token, "access");
+        assert_eq!(tokens.expires_at, 100);
+        assert_eq!(tokens.refresh_token.as_deref(), Some("refresh"));
+    }
+}
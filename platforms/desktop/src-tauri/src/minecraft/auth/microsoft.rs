@@ -0,0 +1,276 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use mcvm::shared::output::MCVMOutput;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::CLIENT_ID;
+use crate::mcvm::MinecraftProfile;
+
+/// Tokens for a Microsoft account. These are only ever stored encrypted.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AccountTokens {
+    /// The Minecraft access token
+    pub access_token: String,
+    /// When the access token expires, as a Unix timestamp in seconds
+    pub expires_at: i64,
+    /// The Microsoft refresh token, used to get a new access token without logging in again
+    pub refresh_token: Option<String>,
+}
+
+impl AccountTokens {
+    /// Whether the access token has expired or is about to
+    pub fn is_expired(&self) -> bool {
+        chrono::Utc::now().timestamp() + EXPIRY_MARGIN >= self.expires_at
+    }
+}
+
+/// The result of logging in to a Microsoft account
+pub struct MicrosoftLogin {
+    pub profile: MinecraftProfile,
+    pub tokens: AccountTokens,
+}
+
+/// Log in to a Microsoft account with the device code flow. The code is shown through the output.
+pub async fn login(
+    client: &Client,
+    output: &mut impl MCVMOutput,
+) -> anyhow::Result<MicrosoftLogin> {
+    let device_code: DeviceCodeResponse = client
+        .post(format!("{MS_AUTH_URL}/devicecode"))
+        .form(&[("client_id", CLIENT_ID), ("scope", SCOPE)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Failed to get device code")?;
+    output.display_special_ms_auth(&device_code.verification_uri, &device_code.user_code);
+
+    let mut interval = Duration::from_secs(device_code.interval.max(1));
+    let deadline = std::time::Instant::now() + Duration::from_secs(device_code.expires_in);
+    let ms_token = loop {
+        tokio::time::sleep(interval).await;
+        if std::time::Instant::now() > deadline {
+            bail!("The login code expired before it was used");
+        }
+        let response = client
+            .post(format!("{MS_AUTH_URL}/token"))
+            .form(&[
+                ("grant_type", DEVICE_CODE_GRANT),
+                ("client_id", CLIENT_ID),
+                ("device_code", &device_code.device_code),
+            ])
+            .send()
+            .await?;
+        if response.status().is_success() {
+            break response
+                .json::<MicrosoftTokenResponse>()
+                .await
+                .context("Failed to parse Microsoft token")?;
+        }
+        let error: MicrosoftErrorResponse = response
+            .json()
+            .await
+            .context("Failed to parse Microsoft error")?;
+        match error.error.as_str() {
+            "authorization_pending" => {}
+            "slow_down" => interval += Duration::from_secs(5),
+            other => bail!("Microsoft login failed: {other}"),
+        }
+    };
+
+    let tokens = minecraft_tokens(client, ms_token).await?;
+    let profile = get_profile(client, &tokens.access_token).await?;
+
+    Ok(MicrosoftLogin { profile, tokens })
+}
+
+/// Get a new access token using a refresh token. Fails with `RefreshTokenRejected` if
+/// Microsoft won't accept the refresh token anymore.
+pub async fn refresh(client: &Client, refresh_token: &str) -> anyhow::Result<AccountTokens> {
+    let response = client
+        .post(format!("{MS_AUTH_URL}/token"))
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", CLIENT_ID),
+            ("scope", SCOPE),
+            ("refresh_token", refresh_token),
+        ])
+        .send()
+        .await
+        .context("Failed to refresh Microsoft token")?;
+    if response.status().is_client_error() {
+        let status = response.status();
+        let error: MicrosoftErrorResponse = response
+            .json()
+            .await
+            .context("Failed to parse Microsoft error")?;
+        if REJECTED_REFRESH_ERRORS.contains(&error.error.as_str()) {
+            return Err(RefreshTokenRejected { error: error.error }.into());
+        }
+        bail!(
+            "Failed to refresh Microsoft token ({status}): {}",
+            error.error
+        );
+    }
+    let ms_token: MicrosoftTokenResponse = response
+        .error_for_status()
+        .context("Failed to refresh Microsoft token")?
+        .json()
+        .await
+        .context("Failed to parse Microsoft token")?;
+    let mut tokens = minecraft_tokens(client, ms_token).await?;
+    // Microsoft doesn't always send a new refresh token, in which case the old one stays valid
+    if tokens.refresh_token.is_none() {
+        tokens.refresh_token = Some(refresh_token.to_string());
+    }
+
+    Ok(tokens)
+}
+
+/// Error when a refresh token was revoked or expired, so the account has to log in again
+#[derive(Debug, Clone)]
+pub struct RefreshTokenRejected {
+    /// The OAuth error code that Microsoft returned
+    pub error: String,
+}
+
+impl Display for RefreshTokenRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Microsoft rejected the refresh token: {}", self.error)
+    }
+}
+
+impl std::error::Error for RefreshTokenRejected {}
+
+/// Exchange a Microsoft token for a Minecraft access token through Xbox Live
+async fn minecraft_tokens(
+    client: &Client,
+    ms_token: MicrosoftTokenResponse,
+) -> anyhow::Result<AccountTokens> {
+    let xbl: XboxResponse = client
+        .post("https://user.auth.xboxlive.com/user/authenticate")
+        .json(&json!({
+            "Properties": {
+                "AuthMethod": "RPS",
+                "SiteName": "user.auth.xboxlive.com",
+                "RpsTicket": format!("d={}", ms_token.access_token),
+            },
+            "RelyingParty": "http://auth.xboxlive.com",
+            "TokenType": "JWT",
+        }))
+        .send()
+        .await?
+        .error_for_status()
+        .context("Failed to authenticate with Xbox Live")?
+        .json()
+        .await?;
+    let xsts: XboxResponse = client
+        .post("https://xsts.auth.xboxlive.com/xsts/authorize")
+        .json(&json!({
+            "Properties": {
+                "SandboxId": "RETAIL",
+                "UserTokens": [xbl.token],
+            },
+            "RelyingParty": "rp://api.minecraftservices.com/",
+            "TokenType": "JWT",
+        }))
+        .send()
+        .await?
+        .error_for_status()
+        .context("Failed to get Xbox Live security token")?
+        .json()
+        .await?;
+    let Some(user_hash) = xsts.display_claims.xui.first().map(|x| &x.uhs) else {
+        bail!("Xbox Live did not return a user hash");
+    };
+
+    let minecraft: MinecraftTokenResponse = client
+        .post(format!("{MC_SERVICES_URL}/authentication/login_with_xbox"))
+        .json(&json!({
+            "identityToken": format!("XBL3.0 x={user_hash};{}", xsts.token),
+        }))
+        .send()
+        .await?
+        .error_for_status()
+        .context("Failed to log in to Minecraft")?
+        .json()
+        .await?;
+
+    Ok(AccountTokens {
+        access_token: minecraft.access_token,
+        expires_at: chrono::Utc::now().timestamp() + minecraft.expires_in,
+        refresh_token: ms_token.refresh_token,
+    })
+}
+
+/// Get the Minecraft profile of an account
+async fn get_profile(client: &Client, access_token: &str) -> anyhow::Result<MinecraftProfile> {
+    client
+        .get(format!("{MC_SERVICES_URL}/minecraft/profile"))
+        .bearer_auth(access_token)
+        .send()
+        .await?
+        .error_for_status()
+        .context("Failed to get Minecraft profile. Does this account own Minecraft?")?
+        .json()
+        .await
+        .context("Failed to parse Minecraft profile")
+}
+
+#[derive(Deserialize)]
+struct DeviceCodeResponse {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+#[derive(Deserialize)]
+struct MicrosoftTokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MicrosoftErrorResponse {
+    error: String,
+}
+
+#[derive(Deserialize)]
+struct XboxResponse {
+    #[serde(rename = "Token")]
+    token: String,
+    #[serde(rename = "DisplayClaims")]
+    display_claims: XboxDisplayClaims,
+}
+
+#[derive(Deserialize)]
+struct XboxDisplayClaims {
+    xui: Vec<XboxUserInfo>,
+}
+
+#[derive(Deserialize)]
+struct XboxUserInfo {
+    uhs: String,
+}
+
+#[derive(Deserialize)]
+struct MinecraftTokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+const MS_AUTH_URL: &str = "https://login.microsoftonline.com/consumers/oauth2/v2.0";
+const MC_SERVICES_URL: &str = "https://api.minecraftservices.com";
+const SCOPE: &str = "XboxLive.signin offline_access";
+/// OAuth errors that mean the refresh token can't be used anymore
+const REJECTED_REFRESH_ERRORS: [&str; 2] = ["invalid_grant", "interaction_required"];
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// How many seconds before the access token expires to treat it as expired
+const EXPIRY_MARGIN: i64 = 60;
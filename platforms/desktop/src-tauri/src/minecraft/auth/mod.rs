@@ -1 +1,3 @@
+pub mod microsoft;
+
 pub const CLIENT_ID: &str = "0cee860d-3586-4214-8dc4-ab45b3ec0a54";
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Event for when the game crashed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameCrashedEvent {
+    pub exit_code: Option<i32>,
+    /// A short description of the crash to show to the user
+    pub summary: String,
+    /// The path to the crash report, if the game wrote one
+    pub crash_report: Option<PathBuf>,
+    pub suspected_mods: Vec<String>,
+}
+
+/// The useful parts of a Minecraft crash report
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct CrashReport {
+    pub description: Option<String>,
+    /// The first line of the exception that caused the crash
+    pub exception: Option<String>,
+    pub suspected_mods: Vec<String>,
+}
+
+/// Get the crash reports that currently exist in an instance
+pub fn list_crash_reports(game_dir: &Path) -> HashSet<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(game_dir.join(CRASH_REPORTS_DIR)) else {
+        return HashSet::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect()
+}
+
+/// Check if the game crashed after it exited, comparing against the crash reports that
+/// existed before it was launched
+pub fn detect_crash(
+    game_dir: &Path,
+    previous_reports: &HashSet<PathBuf>,
+    exit_code: Option<i32>,
+) -> Option<GameCrashedEvent> {
+    // Report filenames contain the time, so the newest one sorts last
+    let new_report = list_crash_reports(game_dir)
+        .into_iter()
+        .filter(|path| !previous_reports.contains(path))
+        .max();
+    let exited_with_error = exit_code.is_some_and(|code| code != 0);
+    if new_report.is_none() && !exited_with_error {
+        return None;
+    }
+
+    let report = new_report
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|text| parse_crash_report(&text))
+        .unwrap_or_default();
+
+    let summary = match (&report.description, &report.exception) {
+        (Some(description), Some(exception)) => format!("{description}: {exception}"),
+        (Some(description), None) => description.clone(),
+        (None, Some(exception)) => exception.clone(),
+        (None, None) => match exit_code {
+            Some(code) => format!("The game exited with code {code}"),
+            None => "The game crashed".into(),
+        },
+    };
+
+    Some(GameCrashedEvent {
+        exit_code,
+        summary,
+        crash_report: new_report,
+        suspected_mods: report.suspected_mods,
+    })
+}
+
+/// Parse the header of a crash report
+pub fn parse_crash_report(text: &str) -> CrashReport {
+    let mut out = CrashReport::default();
+    let mut lines = text.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if let Some(description) = line.strip_prefix("Description:") {
+            out.description = Some(description.trim().to_string());
+            // The exception comes after the blank line following the description
+            out.exception = lines.find(|line| !line.is_empty()).map(str::to_string);
+        } else if let Some(mods) = line
+            .strip_prefix("Suspected Mods:")
+            .or_else(|| line.strip_prefix("Suspected Mod:"))
+        {
+            out.suspected_mods.extend(
+                mods.split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty() && *name != "None")
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    out
+}
+
+/// The directory in the instance where the game writes crash reports
+const CRASH_REPORTS_DIR: &str = "crash-reports";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_crash_report() {
+        let text = "---- Minecraft Crash Report ----
+// Don't be sad, have a hug! <3
+
+Time: 2024-05-01 12:00:00
+Description: Ticking entity
+
+java.lang.NullPointerException: Cannot invoke \"Object.toString()\"
+	at net.minecraft.class_1297.method_5773(class_1297.java:100)
+
+A detailed walkthrough of the error, its code path and all known details is as follows:
+---------------------------------------------------------------------------------------
+Suspected Mods: Paxi (paxi), Fabric API (fabric-api)
+";
+        let report = parse_crash_report(text);
+        assert_eq!(report.description.as_deref(), Some("Ticking entity"));
+        assert_eq!(
+            report.exception.as_deref(),
+            Some("java.lang.NullPointerException: Cannot invoke \"Object.toString()\"")
+        );
+        assert_eq!(
+            report.suspected_mods,
+            vec![
+                "Paxi (paxi)".to_string(),
+                "Fabric API (fabric-api)".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let previous = list_crash_reports(dir.path());
+        assert_eq!(detect_crash(dir.path(), &previous, Some(0)), None);
+
+        let event = detect_crash(dir.path(), &previous, Some(1)).unwrap();
+        assert_eq!(event.crash_report, None);
+
+        let reports = dir.path().join(CRASH_REPORTS_DIR);
+        std::fs::create_dir(&reports).unwrap();
+        let path = reports.join("crash-2024-05-01_12.00.00-client.txt");
+        std::fs::write(&path, "Description: Rendering overlay\n\njava.lang.Error\n").unwrap();
+        let event = detect_crash(dir.path(), &previous, Some(0)).unwrap();
+        assert_eq!(event.crash_report, Some(path));
+        assert_eq!(event.summary, "Rendering overlay: java.lang.Error");
+    }
+}
@@ -0,0 +1,323 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use super::process::GameProcess;
+use crate::tauri_utils::status::GameEvent;
+
+/// A single line of output from the game
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LogLine {
+    pub level: LogLevel,
+    pub time: Option<String>,
+    pub thread: Option<String>,
+    pub logger: Option<String>,
+    pub message: String,
+}
+
+/// Level of a log line. Lines that aren't in the log4j format, like stack traces, are unknown.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+    Unknown,
+}
+
+/// Which output stream of the game a line came from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    /// The line was read from the game's log file because its output couldn't be captured
+    LogFile,
+}
+
+/// Event for a line of game output
+#[derive(Serialize, Clone)]
+pub struct GameLogEvent {
+    pub stream: LogStream,
+    #[serde(flatten)]
+    pub line: LogLine,
+}
+
+/// Parse a line in the log4j format that Minecraft uses, like
+/// `[12:34:56] [Render thread/INFO] (FabricLoader) Loading mods`
+pub fn parse_line(line: &str) -> LogLine {
+    parse_log4j(line).unwrap_or_else(|| LogLine {
+        level: LogLevel::Unknown,
+        time: None,
+        thread: None,
+        logger: None,
+        message: line.to_string(),
+    })
+}
+
+fn parse_log4j(line: &str) -> Option<LogLine> {
+    let (time, rest) = line.strip_prefix('[')?.split_once("] [")?;
+    let (source, rest) = rest.split_once(']')?;
+    let (thread, level) = source.rsplit_once('/')?;
+    let level = match level {
+        "TRACE" => LogLevel::Trace,
+        "DEBUG" => LogLevel::Debug,
+        "INFO" => LogLevel::Info,
+        "WARN" => LogLevel::Warn,
+        "ERROR" => LogLevel::Error,
+        "FATAL" => LogLevel::Fatal,
+        _ => return None,
+    };
+
+    let mut rest = rest.trim_start();
+    let mut logger = None;
+    if let Some(logger_rest) = rest.strip_prefix('(') {
+        if let Some((name, after)) = logger_rest.split_once(')') {
+            logger = Some(name.to_string());
+            rest = after;
+        }
+    }
+    let message = rest.strip_prefix(':').unwrap_or(rest).trim_start();
+
+    Some(LogLine {
+        level,
+        time: Some(time.to_string()),
+        thread: Some(thread.to_string()),
+        logger,
+        message: message.to_string(),
+    })
+}
+
+/// Sends game output to the frontend and to the session log file
+#[derive(Clone)]
+pub struct GameLogger {
+    app: AppHandle,
+    bundle_id: String,
+    session_log: Option<Arc<Mutex<BufWriter<File>>>>,
+}
+
+impl GameLogger {
+    /// Create a logger that writes a new session log in the instance game directory
+    pub fn new(app: AppHandle, bundle_id: String, game_dir: &Path) -> Self {
+        let session_log = match create_session_log(game_dir) {
+            Ok(file) => Some(Arc::new(Mutex::new(BufWriter::new(file)))),
+            Err(e) => {
+                eprintln!("Failed to create session log: {e:?}");
+                None
+            }
+        };
+        Self {
+            app,
+            bundle_id,
+            session_log,
+        }
+    }
+
+    /// Handle a line of output from the game
+    pub fn log(&self, stream: LogStream, line: &str) {
+        if let Some(session_log) = &self.session_log {
+            if let Ok(mut file) = session_log.lock() {
+                let _ = writeln!(file, "{line}");
+                let _ = file.flush();
+            }
+        }
+        let event = GameEvent {
+            bundle_id: self.bundle_id.clone(),
+            event: GameLogEvent {
+                stream,
+                line: parse_line(line),
+            },
+        };
+        let _ = self.app.emit_all("game_log", event);
+    }
+
+    /// Capture the output of the game process. If the output isn't piped, the game's
+    /// latest.log is followed instead until the process exits.
+    pub fn capture(&self, child: &mut Child, process: &GameProcess, game_dir: &Path) {
+        let mut captured = false;
+        if let Some(stdout) = child.stdout.take() {
+            self.read_lines(stdout, LogStream::Stdout);
+            captured = true;
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.read_lines(stderr, LogStream::Stderr);
+            captured = true;
+        }
+
+        if !captured {
+            let logger = self.clone();
+            let process = process.clone();
+            let path = game_dir.join("logs/latest.log");
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = logger.follow_log_file(&path, &process).await {
+                    eprintln!("Failed to follow game log: {e:?}");
+                }
+            });
+        }
+    }
+
+    /// Read lines from an output stream on a separate thread
+    fn read_lines(&self, reader: impl Read + Send + 'static, stream: LogStream) {
+        let logger = self.clone();
+        std::thread::spawn(move || {
+            read_lossy_lines(reader, |line| logger.log(stream, line));
+        });
+    }
+
+    /// Follow the game's log file as it is written
+    async fn follow_log_file(&self, path: &Path, process: &GameProcess) -> anyhow::Result<()> {
+        // The game moves the previous log away when it starts, so skip what is already there
+        let mut position = std::fs::metadata(path).map(|x| x.len()).unwrap_or(0);
+        let mut partial = String::new();
+        loop {
+            let exited = process.has_exited();
+            if let Ok(mut file) = File::open(path) {
+                let len = file
+                    .metadata()
+                    .context("Failed to read log metadata")?
+                    .len();
+                if len < position {
+                    position = 0;
+                    partial.clear();
+                }
+                file.seek(SeekFrom::Start(position))?;
+                let mut bytes = Vec::new();
+                position += file.read_to_end(&mut bytes)? as u64;
+                partial.push_str(&String::from_utf8_lossy(&bytes));
+                while let Some((line, rest)) = partial.split_once('\n') {
+                    self.log(LogStream::LogFile, line.trim_end_matches('\r'));
+                    partial = rest.to_string();
+                }
+            }
+            if exited {
+                return Ok(());
+            }
+            tokio::time::sleep(FOLLOW_INTERVAL).await;
+        }
+    }
+}
+
+/// Call a function with every line from a reader until it ends. The game blocks once
+/// its output pipe is full, so lines that aren't valid UTF-8, like output in the Windows
+/// code page, are converted instead of stopping the reading.
+fn read_lossy_lines(reader: impl Read, mut f: impl FnMut(&str)) {
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf);
+        f(line.trim_end_matches(['\n', '\r']));
+    }
+}
+
+/// Create a new session log file, removing the oldest ones
+fn create_session_log(game_dir: &Path) -> anyhow::Result<File> {
+    let dir = game_dir.join("logs/smithed");
+    std::fs::create_dir_all(&dir).context("Failed to create session log directory")?;
+    prune_session_logs(&dir, MAX_SESSION_LOGS - 1)?;
+
+    let filename = format!("{}.log", chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"));
+    File::create(dir.join(filename)).context("Failed to create session log file")
+}
+
+/// Remove session logs so that only the newest `keep` are left
+fn prune_session_logs(dir: &Path, keep: usize) -> anyhow::Result<()> {
+    let mut logs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .context("Failed to read session log directory")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    // The filenames are timestamps, so sorting them puts the oldest first
+    logs.sort();
+    let remove = logs.len().saturating_sub(keep);
+    for path in &logs[..remove] {
+        std::fs::remove_file(path).context("Failed to remove old session log")?;
+    }
+
+    Ok(())
+}
+
+/// How many session logs to keep for each instance
+const MAX_SESSION_LOGS: usize = 10;
+/// How often to check the log file for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let line = parse_line("[12:34:56] [Render thread/WARN]: Missing texture");
+        assert_eq!(line.level, LogLevel::Warn);
+        assert_eq!(line.time.as_deref(), Some("12:34:56"));
+        assert_eq!(line.thread.as_deref(), Some("Render thread"));
+        assert_eq!(line.logger, None);
+        assert_eq!(line.message, "Missing texture");
+
+        let line = parse_line("[12:34:56] [main/INFO] (FabricLoader) Loading 5 mods");
+        assert_eq!(line.level, LogLevel::Info);
+        assert_eq!(line.logger.as_deref(), Some("FabricLoader"));
+        assert_eq!(line.message, "Loading 5 mods");
+
+        let line = parse_line("\tat net.minecraft.class_310.run(class_310.java:1)");
+        assert_eq!(line.level, LogLevel::Unknown);
+        assert_eq!(
+            line.message,
+            "\tat net.minecraft.class_310.run(class_310.java:1)"
+        );
+    }
+
+    #[test]
+    fn test_read_lossy_lines() {
+        let output: &[u8] = b"[main/INFO]: Caf\xe9\r\nnot utf-8: \xff\nlast";
+        let mut lines = Vec::new();
+        read_lossy_lines(output, |line| lines.push(line.to_string()));
+        assert_eq!(
+            lines,
+            ["[main/INFO]: Caf\u{fffd}", "not utf-8: \u{fffd}", "last"]
+        );
+    }
+
+    #[test]
+    fn test_prune_session_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        for day in 1..=12 {
+            std::fs::write(
+                dir.path().join(format!("2024-01-{day:02}_12-00-00.log")),
+                "",
+            )
+            .unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        prune_session_logs(dir.path(), MAX_SESSION_LOGS).unwrap();
+        let mut left: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left.len(), MAX_SESSION_LOGS + 1);
+        assert_eq!(left[0], "2024-01-03_12-00-00.log");
+        assert!(left.contains(&"notes.txt".to_string()));
+
+        // A new session leaves room for its own log
+        prune_session_logs(dir.path(), MAX_SESSION_LOGS - 1).unwrap();
+        assert!(!dir.path().join("2024-01-03_12-00-00.log").exists());
+        assert!(dir.path().join("2024-01-12_12-00-00.log").exists());
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::api::ApiClient;
+use crate::hashes::ExpectedHash;
+use crate::paths;
+
+/// Manifest of the compatibility mods that need to be installed for each Minecraft version
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModManifest {
+    /// The version of the manifest format
+    pub format_version: u32,
+    /// The revision of the manifest contents. Newer manifests have higher revisions.
+    pub revision: u32,
+    /// Mods for each Minecraft version and modloader
+    pub versions: HashMap<String, HashMap<ModLoader, Vec<ModEntry>>>,
+}
+
+/// A modloader that mods can be installed for
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ModLoader {
+    Fabric,
+    Quilt,
+    Forge,
+    #[serde(rename = "neoforge")]
+    NeoForge,
+}
+
+/// A single mod to install
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ModEntry {
+    /// The display name of the mod
+    pub name: String,
+    /// The filename to install the mod as
+    pub filename: String,
+    pub url: String,
+    pub version: Option<String>,
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+}
+
+impl ModManifest {
+    /// Load the manifest. A manifest file in the config directory takes priority, then
+    /// the manifest from the API if it is newer than the bundled one.
+    pub async fn load(dirs: &ProjectDirs, api: &ApiClient) -> anyhow::Result<Self> {
+        if let Some(manifest) = Self::load_override(dirs)? {
+            return Ok(manifest);
+        }
+
+        let bundled = Self::bundled()?;
+        match Self::fetch(api).await {
+            Ok(remote) => {
+                // Keep the manifest around so that it can be used offline
+                if let Ok(text) = serde_json::to_string(&remote) {
+                    let _ = std::fs::create_dir_all(dirs.cache_dir());
+                    let _ = std::fs::write(Self::cached_path(dirs), text);
+                }
+                Ok(Self::newest(bundled, remote))
+            }
+            Err(e) => {
+                println!("Failed to fetch mod manifest, using local manifest: {e:?}");
+                Self::load_offline(dirs)
+            }
+        }
+    }
+
+    /// Load the manifest without using the network, using the last manifest that was
+    /// fetched from the API if there is one
+    pub fn load_offline(dirs: &ProjectDirs) -> anyhow::Result<Self> {
+        if let Some(manifest) = Self::load_override(dirs)? {
+            return Ok(manifest);
+        }
+
+        let bundled = Self::bundled()?;
+        let cached = Self::read(&Self::cached_path(dirs)).ok();
+        Ok(match cached {
+            Some(cached) => Self::newest(bundled, cached),
+            None => bundled,
+        })
+    }
+
+    fn load_override(dirs: &ProjectDirs) -> anyhow::Result<Option<Self>> {
+        let path = Self::override_path(dirs);
+        if path.exists() {
+            Ok(Some(Self::read(&path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path).context("Failed to open mod manifest")?;
+        let manifest: Self = serde_json::from_reader(BufReader::new(file))
+            .context("Failed to parse mod manifest")?;
+        manifest.check_format()?;
+        Ok(manifest)
+    }
+
+    fn newest(bundled: Self, other: Self) -> Self {
+        if other.revision > bundled.revision {
+            other
+        } else {
+            bundled
+        }
+    }
+
+    /// Get the manifest that is bundled with the launcher
+    pub fn bundled() -> anyhow::Result<Self> {
+        serde_json::from_str(BUNDLED_MANIFEST).context("Failed to parse bundled mod manifest")
+    }
+
+    /// Fetch the newest manifest from the API
+    pub async fn fetch(api: &ApiClient) -> anyhow::Result<Self> {
+        let manifest: Self = api.http.get_json(&api.url(MANIFEST_ENDPOINT)).await?;
+        manifest.check_format()?;
+        Ok(manifest)
+    }
+
+    /// Get the mods to install for a Minecraft version and modloader
+    pub fn get_mods(&self, minecraft_version: &str, loader: ModLoader) -> Option<&[ModEntry]> {
+        self.versions
+            .get(minecraft_version)
+            .and_then(|loaders| loaders.get(&loader))
+            .map(Vec::as_slice)
+    }
+
+    /// Get the mods to install for a Minecraft version and modloader, failing if the
+    /// version is not in the manifest
+    pub fn require_mods(
+        &self,
+        minecraft_version: &str,
+        loader: ModLoader,
+    ) -> Result<&[ModEntry], UnsupportedVersionError> {
+        self.get_mods(minecraft_version, loader)
+            .ok_or_else(|| UnsupportedVersionError {
+                version: minecraft_version.to_string(),
+            })
+    }
+
+    fn check_format(&self) -> anyhow::Result<()> {
+        if self.format_version > FORMAT_VERSION {
+            bail!(
+                "Mod manifest format version {} is newer than the supported version {FORMAT_VERSION}",
+                self.format_version
+            );
+        }
+        // Mods are downloaded from third party hosts, so they always have to be verified
+        for (minecraft_version, loaders) in &self.versions {
+            for entry in loaders.values().flatten() {
+                if entry.expected_hash().is_none() {
+                    bail!(
+                        "Mod '{}' for Minecraft {minecraft_version} in the mod manifest has no checksum",
+                        entry.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn override_path(dirs: &ProjectDirs) -> PathBuf {
+        paths::mod_manifest_override_file(dirs)
+    }
+
+    fn cached_path(dirs: &ProjectDirs) -> PathBuf {
+        paths::cached_mod_manifest_file(dirs)
+    }
+}
+
+impl ModEntry {
+    /// Get the hash that the downloaded mod should have, if the manifest has one
+    pub fn expected_hash(&self) -> Option<ExpectedHash> {
+        ExpectedHash::best(self.sha1.as_ref(), self.sha512.as_ref())
+    }
+}
+
+/// Error when the manifest has no mods for a Minecraft version
+#[derive(Debug, Clone)]
+pub struct UnsupportedVersionError {
+    pub version: String,
+}
+
+impl Display for UnsupportedVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unsupported Minecraft version {}", self.version)
+    }
+}
+
+impl std::error::Error for UnsupportedVersionError {}
+
+/// The newest manifest format version that this launcher understands
+const FORMAT_VERSION: u32 = 1;
+
+/// The API endpoint for the mod manifest
+const MANIFEST_ENDPOINT: &str = "/launcher/mod-manifest";
+
+/// The manifest that is bundled with the launcher
+const BUNDLED_MANIFEST: &str = include_str!("mod_manifest.json");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_manifest() {
+        let manifest = ModManifest::bundled().unwrap();
+        assert!(manifest.format_version <= FORMAT_VERSION);
+
+        let mods = manifest.get_mods("1.20.1", ModLoader::Fabric).unwrap();
+        let names: Vec<_> = mods.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["Paxi", "YUNG's API", "Fabric API"]);
+
+        assert!(manifest.get_mods("1.20.1", ModLoader::Forge).is_none());
+        assert!(manifest.get_mods("1.16.5", ModLoader::Fabric).is_none());
+    }
+
+    #[test]
+    fn test_check_format() {
+        let entry = |sha1: Option<&str>| ModEntry {
+            name: "Paxi".into(),
+            filename: "Smithed_mod_Paxi.jar".into(),
+            url: "https://example.com/paxi.jar".into(),
+            version: None,
+            sha1: sha1.map(str::to_string),
+            sha512: None,
+        };
+        let manifest = |entry: ModEntry| ModManifest {
+            format_version: FORMAT_VERSION,
+            revision: 1,
+            versions: HashMap::from([(
+                "1.20.1".to_string(),
+                HashMap::from([(ModLoader::Fabric, vec![entry])]),
+            )]),
+        };
+
+        let hash = "a".repeat(40);
+        assert!(manifest(entry(Some(&hash))).check_format().is_ok());
+        assert!(manifest(entry(None)).check_format().is_err());
+
+        let mut newer = manifest(entry(Some(&hash)));
+        newer.format_version = FORMAT_VERSION + 1;
+        assert!(newer.check_format().is_err());
+    }
+}
@@ -1,38 +1,57 @@
+mod crash;
+mod logs;
+mod manifest;
 mod mods;
+mod offline;
+mod process;
+
+pub use manifest::{ModLoader, ModManifest, UnsupportedVersionError};
+pub use offline::MissingArtifactsError;
+pub use process::{GameProcess, StopOutcome};
 
 use anyhow::Context;
+use directories::ProjectDirs;
 use mcvm::core::io::files;
-use mcvm::core::user::{User, UserKind};
+use mcvm::core::user::User;
 use mcvm::core::util::versions::MinecraftVersionDeser;
 use mcvm::data::config::instance::{read_instance_config, InstanceConfig};
 use mcvm::data::config::profile::ProfileConfig;
 use mcvm::data::id::{InstanceRef, ProfileID};
-use mcvm::data::instance::Instance;
 use mcvm::data::profile::update::update_profiles;
 use mcvm::data::{config::Config, id::InstanceID};
 use mcvm::io::files::paths::Paths;
 use mcvm::shared::modifications::{ClientType, Proxy};
+use mcvm::shared::output::{MCVMOutput, MessageContents, MessageLevel};
 use mcvm::shared::Side;
 use oauth2::ClientId;
-use reqwest::Client;
 use simple_error::bail;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use crate::api;
+use crate::api::{self, ApiClient};
 use crate::config::LocalBundleConfig;
 use crate::mcvm::output::SmithedMCVMOutput;
+use crate::minecraft::launch::logs::GameLogger;
 use crate::minecraft::launch::mods::install_mods;
+use crate::minecraft::launch::offline::{check_offline_artifacts, InstallRecord};
+use crate::pack_cache::PackCache;
+use crate::tauri_utils::status::GameStatus;
 
 pub async fn launch_bundle(
     bundle_id: String,
     bundle: LocalBundleConfig,
     offline: bool,
-    client: &Client,
+    user: User,
+    api: &ApiClient,
+    pack_cache: &PackCache,
+    project_dirs: &ProjectDirs,
+    process: &GameProcess,
     output: &mut SmithedMCVMOutput,
 ) -> anyhow::Result<()> {
     let instance = InstanceID::from(format!("smithed-bundle-{bundle_id}"));
     let profile_id = ProfileID::from(format!("smithed-bundle-{bundle_id}"));
     let instance_ref = InstanceRef::new(profile_id.clone(), instance.clone());
+    output.set_status(GameStatus::ResolvingPacks);
     let paths: Paths = Paths::new().await?;
 
     let mut config = Config::load(
@@ -42,16 +61,9 @@ pub async fn launch_bundle(
         output,
     )?;
 
-    let user = "smithed-user".to_string();
-    let user_kind = if offline {
-        UserKind::Unverified
-    } else {
-        UserKind::Microsoft { xbox_uid: None }
-    };
-    config
-        .users
-        .add_user(User::new(user_kind, &user, "SmithedUser"));
-    config.users.choose_user(&user)?;
+    let user_id = user.get_id().to_string();
+    config.users.add_user(user);
+    config.users.choose_user(&user_id)?;
 
     if !config.get_instance(&instance_ref).is_some() {
         let instance_config = InstanceConfig::Simple(Side::Client);
@@ -79,23 +91,54 @@ pub async fn launch_bundle(
         config.profiles.insert(profile_id.clone(), profile);
     }
 
-    println!("Updating bundle mcvm profile");
-    let profile_list = [profile_id.clone()];
-    update_profiles(&paths, &mut config, &profile_list, false, false, output).await?;
+    // Offline launches reuse whatever was installed the last time the bundle was launched online
+    if !offline {
+        println!("Updating bundle mcvm profile");
+        output.set_status(GameStatus::UpdatingProfile);
+        let profile_list = [profile_id.clone()];
+        update_profiles(&paths, &mut config, &profile_list, false, false, output).await?;
+    }
 
     if let Some(profile) = config.profiles.get_mut(&instance_ref.profile) {
         let Some(instance) = profile.instances.get_mut(&instance_ref.instance) else {
             bail!("Instance does not exist in profile");
         };
-        install_bundle_packs(&bundle, instance, &paths, client).await?;
-        instance.ensure_dirs(&paths)?;
-        let mods_dir = &instance.get_dirs().get().game_dir.join("mods");
-        files::create_dir(&mods_dir)?;
-        install_mods(client, &mods_dir, &bundle.version)
-            .await
-            .context("Failed to install mods")?;
+        instance
+            .ensure_dirs(&paths)
+            .context("Failed to create instance dirs")?;
+        let game_dir = instance.get_dirs().get().game_dir.clone();
+        let mods_dir = game_dir.join("mods");
+        if offline {
+            let manifest =
+                ModManifest::load_offline(project_dirs).context("Failed to load mod manifest")?;
+            let game_files = game_files(&paths, &bundle.version);
+            check_offline_artifacts(
+                &bundle,
+                &game_dir,
+                &mods_dir,
+                &game_files,
+                pack_cache,
+                &manifest,
+            )?;
+            // Nothing is downloaded offline, so the status stays on resolving the packs
+            install_bundle_packs(&bundle, &game_dir, api, pack_cache, true, output).await?;
+        } else {
+            output.set_status(GameStatus::DownloadingPacks);
+            install_bundle_packs(&bundle, &game_dir, api, pack_cache, false, output).await?;
+            output.set_status(GameStatus::InstallingMods);
+            files::create_dir(&mods_dir)?;
+            let manifest = ModManifest::load(project_dirs, api)
+                .await
+                .context("Failed to load mod manifest")?;
+            install_mods(&api.http, &mods_dir, &bundle.version, &manifest, &*output)
+                .await
+                .context("Failed to install mods")?;
+            InstallRecord::new(&bundle).write(&game_dir)?;
+        }
 
-        let mut handle = instance
+        let previous_crash_reports = crash::list_crash_reports(&game_dir);
+        output.set_status(GameStatus::Authenticating);
+        let handle = instance
             .launch(
                 &paths,
                 &mut config.users,
@@ -104,7 +147,27 @@ pub async fn launch_bundle(
                 output,
             )
             .await?;
-        handle.wait()?;
+        // There is no await between starting the game and registering it, so the launch
+        // task can't be cancelled in between and leave the game untracked
+        let mut child = handle.get_process();
+        GameLogger::new(output.app_handle().clone(), bundle_id.clone(), &game_dir)
+            .capture(&mut child, process, &game_dir);
+        process.set(child)?;
+        output.set_status(GameStatus::Running);
+        let code = process
+            .wait()
+            .await
+            .context("Failed to wait for the game process")?;
+        output.set_status(GameStatus::Exited { code });
+        if !process.stop_requested() {
+            if let Some(event) = crash::detect_crash(&game_dir, &previous_crash_reports, code) {
+                output.display(
+                    MessageContents::Error(format!("The game crashed: {}", event.summary)),
+                    MessageLevel::Important,
+                );
+                output.emit_game_event("game_crashed", event);
+            }
+        }
     } else {
         bail!("Unknown instance '{}'", instance);
     }
@@ -112,17 +175,23 @@ pub async fn launch_bundle(
     Ok(())
 }
 
-/// Install the packs on a bundle instance
+/// The files installed by mcvm that launching a version needs: the client jar and libraries
+fn game_files(paths: &Paths, version: &str) -> Vec<PathBuf> {
+    vec![
+        paths.jars.join(format!("{version}_client.jar")),
+        paths.libraries.clone(),
+    ]
+}
+
+/// Install the packs on a bundle instance. When offline, only cached packs are used.
 async fn install_bundle_packs(
     bundle: &LocalBundleConfig,
-    instance: &mut Instance,
-    paths: &Paths,
-    client: &Client,
+    game_dir: &Path,
+    api: &ApiClient,
+    pack_cache: &PackCache,
+    offline: bool,
+    output: &mut SmithedMCVMOutput,
 ) -> anyhow::Result<()> {
-    instance
-        .ensure_dirs(&paths)
-        .context("Failed to create instance dirs")?;
-    let game_dir = &instance.get_dirs().get().game_dir;
     let paxi_dir = game_dir.join("config/paxi");
     files::create_leading_dirs(&paxi_dir).context("Failed to create leading dirs for Paxi dir")?;
     files::create_dir(&paxi_dir).context("Failed to create Paxi dir")?;
@@ -131,13 +200,39 @@ async fn install_bundle_packs(
     let resource_packs_dir = paxi_dir.join("resourcepacks");
     files::create_dir(&resource_packs_dir).context("Failed to create Paxi resource packs dir")?;
 
-    api::download_packs(
-        client,
-        bundle.packs.clone(),
-        &datapacks_dir,
-        &resource_packs_dir,
-    )
-    .await?;
+    let conflicts = if offline {
+        // If the packs were evicted from the cache, the ones that are already installed are used
+        api::install_cached_packs(
+            &bundle.packs,
+            &bundle.version,
+            pack_cache,
+            &datapacks_dir,
+            &resource_packs_dir,
+        )?
+        .unwrap_or_default()
+    } else {
+        api::download_packs(
+            api,
+            bundle.packs.clone(),
+            &bundle.version,
+            pack_cache,
+            &datapacks_dir,
+            &resource_packs_dir,
+            &*output,
+        )
+        .await?
+    };
+    for conflict in conflicts {
+        output.display(
+            MessageContents::Warning(format!(
+                "Conflict in '{}': using the file from '{}' over {}",
+                conflict.path,
+                conflict.winner,
+                conflict.overridden.join(", ")
+            )),
+            MessageLevel::Important,
+        );
+    }
 
     Ok(())
 }
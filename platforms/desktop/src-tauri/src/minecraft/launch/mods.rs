@@ -1,85 +1,210 @@
+use std::collections::HashMap;
 use std::path::Path;
 
-use anyhow::{bail, Context};
-use mcvm::core::net::download;
-use reqwest::Client;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use super::manifest::{ModEntry, ModLoader, ModManifest};
+use crate::files;
+use crate::hashes::{self, ExpectedHash};
+use crate::http::{DownloadProgress, HttpClient};
 
 /// Install mods so that datapacks work
 pub async fn install_mods(
-    client: &Client,
+    http: &HttpClient,
     mods_dir: &Path,
     mc_version: &str,
+    manifest: &ModManifest,
+    progress: &impl DownloadProgress,
 ) -> anyhow::Result<()> {
     // We need Paxi for global datapacks and resource packs, which depends on Yung's API and Fabric API
-    let (paxi, yungs, fabric_api) = match mc_version {
-        "1.17" => (
-			"https://www.curseforge.com/api/v1/mods/418881/files/3120081/download",
-            None,
-            "https://cdn.modrinth.com/data/P7dR8mSH/versions/0.36.0%2B1.17/fabric-api-0.36.0%2B1.17.jar"
-		),
-        "1.17.1" => (
-			"https://www.curseforge.com/api/v1/mods/418881/files/3425423/download",
-            None,
-            "https://cdn.modrinth.com/data/P7dR8mSH/versions/0.46.1%2B1.17/fabric-api-0.46.1%2B1.17.jar"
-		),
-        "1.18" => (
-			"https://www.curseforge.com/api/v1/mods/418881/files/3631270/download",
-            None,
-            "https://cdn.modrinth.com/data/P7dR8mSH/versions/0.44.0%2B1.18/fabric-api-0.44.0%2B1.18.jar"
-		),
-		"1.18.1" => (
-			"https://www.curseforge.com/api/v1/mods/418881/files/3631270/download",
-            Some("https://www.curseforge.com/api/v1/mods/421649/files/4428185/download"),
-            "https://cdn.modrinth.com/data/P7dR8mSH/versions/0.46.6%2B1.18/fabric-api-0.46.6%2B1.18.jar"
-		),
-        "1.18.2" => (
-			"https://www.curseforge.com/api/v1/mods/418881/files/3706642/download",
-            Some("https://www.curseforge.com/api/v1/mods/421649/files/4428185/download"),
-            "https://cdn.modrinth.com/data/P7dR8mSH/versions/95QMsRyb/fabric-api-0.76.0%2B1.18.2.jar"
-		),
-        "1.19" => (
-			"https://cdn.modrinth.com/data/CU0PAyzb/versions/PGslvGfk/Paxi-1.19.2-Fabric-3.0.jar",
-            Some("https://cdn.modrinth.com/data/Ua7DFN59/versions/IxuGYnWF/YungsApi-1.19.2-Fabric-3.8.9.jar"),
-            "https://cdn.modrinth.com/data/P7dR8mSH/versions/0.58.0%2B1.19/fabric-api-0.58.0%2B1.19.jar"
-		),
-        "1.19.2" => (
-			"https://cdn.modrinth.com/data/CU0PAyzb/versions/PGslvGfk/Paxi-1.19.2-Fabric-3.0.jar",
-            Some("https://cdn.modrinth.com/data/Ua7DFN59/versions/IxuGYnWF/YungsApi-1.19.2-Fabric-3.8.9.jar"),
-            "https://cdn.modrinth.com/data/P7dR8mSH/versions/fO05PwUR/fabric-api-0.76.1%2B1.19.2.jar"
-		),
-        "1.19.4" => (
-			"https://cdn.modrinth.com/data/CU0PAyzb/versions/t0EvcKWk/Paxi-1.19.4-Fabric-3.2.0.jar",
-			Some("https://cdn.modrinth.com/data/Ua7DFN59/versions/h32n7OPC/YungsApi-1.19.4-Fabric-3.10.1.jar"),
-			"https://cdn.modrinth.com/data/P7dR8mSH/versions/LKgVmlZB/fabric-api-0.87.0%2B1.19.4.jar"
-		),
-    	"1.20" => (
-			"https://cdn.modrinth.com/data/CU0PAyzb/versions/UVPLKCqf/Paxi-1.20-Fabric-4.0.jar",
-			Some("https://cdn.modrinth.com/data/Ua7DFN59/versions/NmrTF2A5/YungsApi-1.20-Fabric-4.0.1.jar"),
-			"https://cdn.modrinth.com/data/P7dR8mSH/versions/n2c5lxAo/fabric-api-0.83.0%2B1.20.jar"
-		),
-		"1.20.1" => (
-			"https://cdn.modrinth.com/data/CU0PAyzb/versions/UVPLKCqf/Paxi-1.20-Fabric-4.0.jar",
-			Some("https://cdn.modrinth.com/data/Ua7DFN59/versions/NmrTF2A5/YungsApi-1.20-Fabric-4.0.1.jar"),
-			"https://cdn.modrinth.com/data/P7dR8mSH/versions/1sf8i9fy/fabric-api-0.89.0%2B1.20.1.jar"
-		),
-		_ => bail!("Unsupported Minecraft version"),
-	};
-
-    let paxi_path = mods_dir.join("Smithed_mod_Paxi.jar");
-    let yungs_path = mods_dir.join("Smithed_mod_YUNGS_API.jar");
-    let fabric_api_path = mods_dir.join("Smithed_mod_Fabric_API.jar");
-
-    download::file(paxi, &paxi_path, client)
-        .await
-        .context("Failed to download Paxi")?;
-    if let Some(yungs) = yungs {
-        download::file(yungs, &yungs_path, client)
+    let mods = manifest.require_mods(mc_version, ModLoader::Fabric)?;
+
+    let mut lockfile = ModLockfile::open(mods_dir)?;
+
+    // Remove mods that we installed before but aren't needed anymore
+    let stale: Vec<_> = lockfile
+        .mods
+        .keys()
+        .filter(|filename| !mods.iter().any(|entry| &entry.filename == *filename))
+        .cloned()
+        .collect();
+    if !stale.is_empty() {
+        for filename in stale {
+            let path = mods_dir.join(&filename);
+            if path.exists() {
+                std::fs::remove_file(&path).context("Failed to remove old mod")?;
+            }
+            lockfile.mods.remove(&filename);
+        }
+        lockfile.write(mods_dir)?;
+    }
+
+    for entry in mods {
+        let path = mods_dir.join(&entry.filename);
+        if lockfile.is_current(entry, &path) {
+            println!("{} is already installed", entry.name);
+            continue;
+        }
+
+        let bytes = http
+            .download(&entry.url, &entry.filename, progress)
             .await
-            .context("Failed to download YUNG's API")?;
+            .with_context(|| format!("Failed to download {}", entry.name))?;
+        if let Some(expected_hash) = entry.expected_hash() {
+            expected_hash.verify(&bytes, &entry.filename)?;
+        }
+        files::write_replace(&path, &bytes)
+            .with_context(|| format!("Failed to write {}", entry.name))?;
+
+        lockfile.mods.insert(
+            entry.filename.clone(),
+            LockedMod {
+                url: entry.url.clone(),
+                version: entry.version.clone(),
+                sha1: hashes::sha1(&bytes),
+            },
+        );
+        // Record each mod as soon as it is installed so that a failed launch doesn't
+        // have to download it again
+        lockfile.write(mods_dir)?;
     }
-    download::file(fabric_api, &fabric_api_path, client)
-        .await
-        .context("Failed to download Fabric API")?;
 
     Ok(())
 }
+
+/// Get the names of the mods that would need to be downloaded by install_mods
+pub fn missing_mods(
+    mods_dir: &Path,
+    mc_version: &str,
+    manifest: &ModManifest,
+) -> anyhow::Result<Vec<String>> {
+    let mods = manifest.require_mods(mc_version, ModLoader::Fabric)?;
+    let lockfile = ModLockfile::open(mods_dir)?;
+
+    Ok(mods
+        .iter()
+        .filter(|entry| !lockfile.is_current(entry, &mods_dir.join(&entry.filename)))
+        .map(|entry| entry.name.clone())
+        .collect())
+}
+
+/// Record of the mods that were installed into a mods directory
+#[derive(Serialize, Deserialize, Default)]
+struct ModLockfile {
+    /// Installed mods by their filename
+    mods: HashMap<String, LockedMod>,
+}
+
+/// A single installed mod
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct LockedMod {
+    url: String,
+    version: Option<String>,
+    /// The SHA-1 hash of the installed file
+    sha1: String,
+}
+
+impl ModLockfile {
+    /// Opens the lockfile in a mods directory. An unreadable lockfile is treated as empty.
+    fn open(mods_dir: &Path) -> anyhow::Result<Self> {
+        let path = mods_dir.join(LOCKFILE_FILENAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path).context("Failed to read mod lockfile")?;
+        Ok(serde_json::from_str(&text).unwrap_or_default())
+    }
+
+    fn write(&self, mods_dir: &Path) -> anyhow::Result<()> {
+        let text =
+            serde_json::to_string_pretty(self).context("Failed to serialize mod lockfile")?;
+        files::write_replace(&mods_dir.join(LOCKFILE_FILENAME), text.as_bytes())
+            .context("Failed to write mod lockfile")?;
+        Ok(())
+    }
+
+    /// Check whether a mod is installed from the same source and the file hasn't been changed
+    fn is_current(&self, entry: &ModEntry, path: &Path) -> bool {
+        let Some(locked) = self.mods.get(&entry.filename) else {
+            return false;
+        };
+        if locked.url != entry.url || locked.version != entry.version {
+            return false;
+        }
+        // If the manifest has a hash, the installed file has to match it too
+        if let Some(expected_hash) = entry.expected_hash() {
+            if !expected_hash.matches_file(path) {
+                return false;
+            }
+        }
+        ExpectedHash::Sha1(locked.sha1.clone()).matches_file(path)
+    }
+}
+
+/// The filename of the lockfile in the mods directory
+const LOCKFILE_FILENAME: &str = "smithed_mods.lock.json";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(url: &str, sha1: Option<String>) -> ModEntry {
+        ModEntry {
+            name: "Paxi".into(),
+            filename: "Smithed_mod_Paxi.jar".into(),
+            url: url.into(),
+            version: Some("1.0".into()),
+            sha1,
+            sha512: None,
+        }
+    }
+
+    #[test]
+    fn test_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Smithed_mod_Paxi.jar");
+        std::fs::write(&path, b"paxi").unwrap();
+        let sha1 = hashes::sha1(b"paxi");
+
+        let mut lockfile = ModLockfile::open(dir.path()).unwrap();
+        assert!(lockfile.mods.is_empty());
+        lockfile.mods.insert(
+            "Smithed_mod_Paxi.jar".into(),
+            LockedMod {
+                url: "https://example.com/paxi.jar".into(),
+                version: Some("1.0".into()),
+                sha1: sha1.clone(),
+            },
+        );
+        lockfile.write(dir.path()).unwrap();
+        let lockfile = ModLockfile::open(dir.path()).unwrap();
+        assert_eq!(
+            lockfile.mods["Smithed_mod_Paxi.jar"].url,
+            "https://example.com/paxi.jar"
+        );
+
+        // Installed from the same source, with and without a hash in the manifest
+        assert!(lockfile.is_current(&entry("https://example.com/paxi.jar", None), &path));
+        assert!(lockfile.is_current(
+            &entry("https://example.com/paxi.jar", Some(sha1.clone())),
+            &path
+        ));
+        // The manifest points somewhere else or expects a different file
+        assert!(!lockfile.is_current(&entry("https://example.com/paxi2.jar", None), &path));
+        assert!(!lockfile.is_current(
+            &entry("https://example.com/paxi.jar", Some("0".repeat(40))),
+            &path
+        ));
+        // The installed file was changed
+        std::fs::write(&path, b"changed").unwrap();
+        assert!(!lockfile.is_current(&entry("https://example.com/paxi.jar", None), &path));
+    }
+
+    #[test]
+    fn test_unreadable_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(LOCKFILE_FILENAME), "{").unwrap();
+        assert!(ModLockfile::open(dir.path()).unwrap().mods.is_empty());
+    }
+}
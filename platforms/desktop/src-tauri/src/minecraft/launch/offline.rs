@@ -0,0 +1,237 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use super::manifest::ModManifest;
+use super::mods::missing_mods;
+use crate::config::LocalBundleConfig;
+use crate::files;
+use crate::pack_cache::PackCache;
+
+/// Record of the last time a bundle instance was fully set up while online
+#[derive(Serialize, Deserialize)]
+pub struct InstallRecord {
+    pub minecraft_version: String,
+    /// The pack cache key of the packs that were installed
+    pub packs_key: String,
+}
+
+impl InstallRecord {
+    /// Create the record for a bundle
+    pub fn new(bundle: &LocalBundleConfig) -> Self {
+        Self {
+            minecraft_version: bundle.version.clone(),
+            packs_key: PackCache::key(&bundle.packs, &bundle.version),
+        }
+    }
+
+    /// Read the record from an instance game directory
+    pub fn read(game_dir: &Path) -> Option<Self> {
+        let text = std::fs::read_to_string(game_dir.join(RECORD_FILENAME)).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    /// Write the record to an instance game directory
+    pub fn write(&self, game_dir: &Path) -> anyhow::Result<()> {
+        let text =
+            serde_json::to_string_pretty(self).context("Failed to serialize install record")?;
+        files::write_replace(&game_dir.join(RECORD_FILENAME), text.as_bytes())
+            .context("Failed to write install record")
+    }
+}
+
+/// Error when a bundle can't be launched offline because it hasn't been fully set up
+#[derive(Debug, Clone)]
+pub struct MissingArtifactsError {
+    /// Descriptions of the artifacts that are missing
+    pub missing: Vec<String>,
+}
+
+impl Display for MissingArtifactsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot launch offline because these have not been downloaded yet: {}",
+            self.missing.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for MissingArtifactsError {}
+
+/// Check that everything needed to launch a bundle without the network is already installed.
+/// The game files are the version jar and libraries directory that the launch needs.
+pub fn check_offline_artifacts(
+    bundle: &LocalBundleConfig,
+    game_dir: &Path,
+    mods_dir: &Path,
+    game_files: &[PathBuf],
+    pack_cache: &PackCache,
+    manifest: &ModManifest,
+) -> anyhow::Result<()> {
+    let mut missing = Vec::new();
+    let record = InstallRecord::read(game_dir);
+    let expected = InstallRecord::new(bundle);
+
+    // The record can outlive the game files if they were deleted since the last online launch
+    let game_installed = record
+        .as_ref()
+        .is_some_and(|record| record.minecraft_version == expected.minecraft_version)
+        && game_files.iter().all(|path| path.exists());
+    if !game_installed {
+        missing.push(format!("Minecraft {} game files", bundle.version));
+    }
+
+    let packs_installed = bundle.packs.is_empty()
+        || pack_cache.get(&expected.packs_key).is_some()
+        || record.is_some_and(|record| record.packs_key == expected.packs_key);
+    if !packs_installed {
+        missing.push("Welded bundle packs".into());
+    }
+
+    for name in missing_mods(mods_dir, &bundle.version, manifest)? {
+        missing.push(format!("{name} mod"));
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(MissingArtifactsError { missing }.into())
+    }
+}
+
+/// The filename of the install record in the instance game directory
+const RECORD_FILENAME: &str = "smithed_install.json";
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::api_types::PackReference;
+    use crate::hashes;
+    use crate::minecraft::launch::manifest::{ModEntry, ModLoader};
+
+    fn make_bundle(version: &str, pack_id: &str) -> LocalBundleConfig {
+        LocalBundleConfig {
+            version: version.into(),
+            packs: vec![PackReference {
+                id: pack_id.into(),
+                version: "1".into(),
+            }],
+        }
+    }
+
+    fn manifest() -> ModManifest {
+        let entry = ModEntry {
+            name: "Paxi".into(),
+            filename: "Smithed_mod_Paxi.jar".into(),
+            url: "https://example.com/paxi.jar".into(),
+            version: None,
+            sha1: Some(hashes::sha1(b"paxi")),
+            sha512: None,
+        };
+        ModManifest {
+            format_version: 1,
+            revision: 1,
+            versions: HashMap::from([(
+                "1.20.1".to_string(),
+                HashMap::from([(ModLoader::Fabric, vec![entry])]),
+            )]),
+        }
+    }
+
+    /// Get the artifacts that check_offline_artifacts says are missing
+    fn missing(
+        bundle: &LocalBundleConfig,
+        game_dir: &Path,
+        mods_dir: &Path,
+        cache: &PackCache,
+    ) -> Vec<String> {
+        let game_files = [game_dir.join("client.jar")];
+        match check_offline_artifacts(bundle, game_dir, mods_dir, &game_files, cache, &manifest()) {
+            Ok(()) => Vec::new(),
+            Err(e) => e.downcast::<MissingArtifactsError>().unwrap().missing,
+        }
+    }
+
+    #[test]
+    fn test_check_offline_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let game_dir = dir.path().join("game");
+        let mods_dir = game_dir.join("mods");
+        std::fs::create_dir_all(&mods_dir).unwrap();
+        let cache = PackCache::with_dir(dir.path().join("cache"), u64::MAX);
+        let bundle = make_bundle("1.20.1", "foo");
+
+        // Nothing has been installed yet
+        assert_eq!(
+            missing(&bundle, &game_dir, &mods_dir, &cache),
+            [
+                "Minecraft 1.20.1 game files",
+                "Welded bundle packs",
+                "Paxi mod"
+            ]
+        );
+
+        // Everything is installed
+        InstallRecord::new(&bundle).write(&game_dir).unwrap();
+        std::fs::write(game_dir.join("client.jar"), b"jar").unwrap();
+        std::fs::write(mods_dir.join("Smithed_mod_Paxi.jar"), b"paxi").unwrap();
+        let lockfile = serde_json::json!({"mods": {"Smithed_mod_Paxi.jar": {
+            "url": "https://example.com/paxi.jar",
+            "version": null,
+            "sha1": hashes::sha1(b"paxi"),
+        }}});
+        std::fs::write(
+            mods_dir.join("smithed_mods.lock.json"),
+            lockfile.to_string(),
+        )
+        .unwrap();
+        assert!(missing(&bundle, &game_dir, &mods_dir, &cache).is_empty());
+
+        // The welded packs are in the cache even though the record is for other packs
+        let other = make_bundle("1.20.1", "bar");
+        InstallRecord::new(&other).write(&game_dir).unwrap();
+        assert_eq!(
+            missing(&bundle, &game_dir, &mods_dir, &cache),
+            ["Welded bundle packs"]
+        );
+        let key = PackCache::key(&bundle.packs, &bundle.version);
+        cache
+            .insert(
+                &key,
+                &bundle.packs,
+                &bundle.version,
+                Some(b"pack"),
+                None,
+                &[],
+            )
+            .unwrap();
+        assert!(missing(&bundle, &game_dir, &mods_dir, &cache).is_empty());
+
+        // The record is for another Minecraft version
+        InstallRecord::new(&make_bundle("1.20", "foo"))
+            .write(&game_dir)
+            .unwrap();
+        assert_eq!(
+            missing(&bundle, &game_dir, &mods_dir, &cache),
+            ["Minecraft 1.20.1 game files"]
+        );
+        InstallRecord::new(&bundle).write(&game_dir).unwrap();
+
+        // The game jar was deleted even though the record says that the game is installed
+        std::fs::remove_file(game_dir.join("client.jar")).unwrap();
+        assert_eq!(
+            missing(&bundle, &game_dir, &mods_dir, &cache),
+            ["Minecraft 1.20.1 game files"]
+        );
+        std::fs::write(game_dir.join("client.jar"), b"jar").unwrap();
+
+        // The mod jar was deleted
+        std::fs::remove_file(mods_dir.join("Smithed_mod_Paxi.jar")).unwrap();
+        assert_eq!(missing(&bundle, &game_dir, &mods_dir, &cache), ["Paxi mod"]);
+    }
+}
@@ -0,0 +1,228 @@
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+/// The running game process, shared between the launch task and the stop command.
+/// It is empty until the game has actually been started.
+#[derive(Clone, Default)]
+pub struct GameProcess {
+    inner: Arc<Mutex<Option<Child>>>,
+    stop_requested: Arc<AtomicBool>,
+}
+
+/// What happened when stopping the game
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StopOutcome {
+    /// Whether a game process was running when it was stopped
+    pub was_running: bool,
+    /// Whether the process has exited
+    pub exited: bool,
+    /// Whether the process had to be killed after the grace period ran out
+    pub forced: bool,
+}
+
+impl StopOutcome {
+    /// The outcome when there was no running game to stop
+    pub fn not_running() -> Self {
+        Self {
+            was_running: false,
+            exited: true,
+            forced: false,
+        }
+    }
+}
+
+impl GameProcess {
+    /// Store the child process once the game has been started. If the game was stopped
+    /// while it was starting, the process is killed right away so that it isn't left running.
+    pub fn set(&self, mut child: Child) -> anyhow::Result<()> {
+        let mut lock = self.lock()?;
+        // Stopping sets the flag before it looks for the process, so checking it while
+        // holding the lock means that one of the two always sees the other
+        if self.stop_requested() {
+            println!("Game was stopped while it was starting, killing it");
+            let _ = kill(&mut child);
+        }
+        *lock = Some(child);
+        Ok(())
+    }
+
+    /// Get the PID of the process, if it has been started
+    pub fn pid(&self) -> Option<u32> {
+        self.lock().ok()?.as_ref().map(Child::id)
+    }
+
+    /// Wait for the process to exit without blocking the async runtime.
+    /// Returns the exit code, which is missing if the process was killed by a signal.
+    pub async fn wait(&self) -> anyhow::Result<Option<i32>> {
+        loop {
+            if let Some(code) = self.try_wait()? {
+                return Ok(code);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Whether the process has exited. A process that can't be checked is treated as exited.
+    pub fn has_exited(&self) -> bool {
+        self.try_wait().map_or(true, |code| code.is_some())
+    }
+
+    /// Whether this is the same game process as another one
+    pub fn is_same(&self, other: &GameProcess) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Whether the process was stopped by the user instead of exiting by itself
+    pub fn stop_requested(&self) -> bool {
+        self.stop_requested.load(Ordering::SeqCst)
+    }
+
+    /// Ask the process to exit, and kill it if it hasn't exited after the grace period
+    pub async fn stop(&self, grace_period: Duration) -> anyhow::Result<StopOutcome> {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        let Some(pid) = self.pid() else {
+            return Ok(StopOutcome::not_running());
+        };
+        if self.try_wait()?.is_some() {
+            return Ok(StopOutcome::not_running());
+        }
+
+        if let Err(e) = terminate(pid) {
+            eprintln!("Failed to ask the game to exit: {e:?}");
+        }
+        let start = Instant::now();
+        while start.elapsed() < grace_period {
+            if self.try_wait()?.is_some() {
+                return Ok(StopOutcome {
+                    was_running: true,
+                    exited: true,
+                    forced: false,
+                });
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        println!("Game did not exit after {grace_period:?}, killing it");
+        if let Some(child) = self.lock()?.as_mut() {
+            // Killing fails if the process exited in the meantime, which is fine
+            let _ = kill(child);
+        }
+        // Give the OS a moment to clean up the killed process
+        let start = Instant::now();
+        while start.elapsed() < KILL_TIMEOUT {
+            if self.try_wait()?.is_some() {
+                return Ok(StopOutcome {
+                    was_running: true,
+                    exited: true,
+                    forced: true,
+                });
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        Ok(StopOutcome {
+            was_running: true,
+            exited: false,
+            forced: true,
+        })
+    }
+
+    /// Check if the process has exited. The outer option is None if it is still running.
+    fn try_wait(&self) -> anyhow::Result<Option<Option<i32>>> {
+        let mut lock = self.lock()?;
+        let Some(child) = lock.as_mut() else {
+            return Ok(None);
+        };
+        let status = child
+            .try_wait()
+            .context("Failed to check the game process")?;
+        Ok(status.map(|status| status.code()))
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, Option<Child>>> {
+        self.inner
+            .lock()
+            .map_err(|_| anyhow!("Game process lock was poisoned"))
+    }
+}
+
+/// Ask a process to exit gracefully
+fn terminate(pid: u32) -> anyhow::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("taskkill");
+        // Stop the whole process tree so that the game's child processes don't keep running
+        command.arg("/T").arg("/PID").arg(pid.to_string());
+        command
+    } else {
+        let mut command = Command::new("kill");
+        command.arg("-TERM").arg(pid.to_string());
+        command
+    };
+    command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to run the terminate command")?;
+
+    Ok(())
+}
+
+/// Kill a process right away. On Windows this kills the whole process tree, since killing
+/// only the child would leave the processes that it started running.
+fn kill(child: &mut Child) -> anyhow::Result<()> {
+    if cfg!(windows) {
+        let status = Command::new("taskkill")
+            .arg("/F")
+            .arg("/T")
+            .arg("/PID")
+            .arg(child.id().to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context("Failed to run taskkill")?;
+        if status.success() {
+            return Ok(());
+        }
+    }
+    child.kill().context("Failed to kill the game process")
+}
+
+/// How often to check if the process has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long to wait for the process to exit after killing it
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stop() {
+        let process = GameProcess::default();
+        let child = Command::new("sleep").arg("30").spawn().unwrap();
+        process.set(child).unwrap();
+
+        let outcome = process.stop(Duration::from_secs(5)).await.unwrap();
+        assert!(outcome.was_running);
+        assert!(outcome.exited);
+        assert!(!outcome.forced);
+        assert_eq!(process.wait().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_stop_before_start() {
+        let process = GameProcess::default();
+        let outcome = process.stop(Duration::from_secs(5)).await.unwrap();
+        assert!(!outcome.was_running);
+
+        // The game finished starting after it was stopped
+        let child = Command::new("sleep").arg("30").spawn().unwrap();
+        process.set(child).unwrap();
+        assert_eq!(process.wait().await.unwrap(), None);
+    }
+}
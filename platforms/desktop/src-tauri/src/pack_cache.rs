@@ -0,0 +1,390 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use chrono::Utc;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::api_types::PackReference;
+use crate::hashes::{self, ExpectedHash};
+use crate::paths;
+use crate::weld::WeldConflict;
+
+/// On-disk cache of welded packs, keyed by the set of packs and the Minecraft version
+#[derive(Clone)]
+pub struct PackCache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+/// The cached welded packs for a single cache key
+#[derive(Clone, Debug, Default)]
+pub struct CachedPacks {
+    pub datapack: Option<CachedPack>,
+    pub resource_pack: Option<CachedPack>,
+    /// The conflicts that were found when the packs were welded
+    pub conflicts: Vec<WeldConflict>,
+}
+
+/// A single cached welded pack
+#[derive(Clone, Debug)]
+pub struct CachedPack {
+    pub path: PathBuf,
+    /// The SHA-1 hash of the pack
+    pub sha1: String,
+}
+
+/// Metadata stored alongside each cache entry
+#[derive(Serialize, Deserialize)]
+struct CacheEntryMeta {
+    /// The version of the entry format. Entries from before it existed are 0.
+    #[serde(default)]
+    format_version: u32,
+    minecraft_version: String,
+    packs: Vec<PackReference>,
+    datapack_sha1: Option<String>,
+    resource_pack_sha1: Option<String>,
+    #[serde(default)]
+    conflicts: Vec<WeldConflict>,
+    /// Unix timestamp of the last time this entry was used
+    last_used: i64,
+}
+
+impl PackCache {
+    /// Creates the pack cache in the default cache directory
+    pub fn new(dirs: &ProjectDirs) -> Self {
+        Self::with_dir(paths::pack_cache_dir(dirs), DEFAULT_MAX_CACHE_SIZE)
+    }
+
+    /// Creates the pack cache in a custom directory with a size limit in bytes
+    pub fn with_dir(dir: PathBuf, max_size: u64) -> Self {
+        Self { dir, max_size }
+    }
+
+    /// Get the cache key for a list of packs and a Minecraft version.
+    /// The order of the packs is part of the key because earlier packs win weld conflicts.
+    pub fn key(packs: &[PackReference], minecraft_version: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(minecraft_version.as_bytes());
+        for pack in packs {
+            hasher.update(b"\n");
+            hasher.update(format!("{}@{}", pack.id, pack.version).as_bytes());
+        }
+
+        hex::encode(hasher.finalize())
+    }
+
+    /// Get the cached packs for a key, marking the entry as recently used.
+    /// Entries with files that don't match their hashes are removed.
+    pub fn get(&self, key: &str) -> Option<CachedPacks> {
+        let entry_dir = self.dir.join(key);
+        let meta_path = entry_dir.join(META_FILENAME);
+        let mut meta = read_meta(&meta_path).ok()?;
+        // Older entries have no hashes, so they can't be verified
+        if meta.format_version != ENTRY_FORMAT_VERSION {
+            println!("Cached welded packs are from an older launcher, removing them");
+            let _ = std::fs::remove_dir_all(&entry_dir);
+            return None;
+        }
+
+        let packs = Self::entry_packs(&entry_dir, &meta);
+        let verified = [
+            (&packs.datapack, &meta.datapack_sha1),
+            (&packs.resource_pack, &meta.resource_pack_sha1),
+        ]
+        .into_iter()
+        .all(|(pack, sha1)| match pack {
+            Some(pack) => ExpectedHash::Sha1(pack.sha1.clone()).matches_file(&pack.path),
+            None => sha1.is_none(),
+        });
+        if !verified {
+            println!("Cached welded packs are corrupted, removing them");
+            let _ = std::fs::remove_dir_all(&entry_dir);
+            return None;
+        }
+
+        meta.last_used = Utc::now().timestamp();
+        // Failing to update the usage time only affects eviction order
+        let _ = write_meta(&meta_path, &meta);
+
+        Some(packs)
+    }
+
+    /// Store welded packs in the cache, evicting old entries if the cache is too large
+    pub fn insert(
+        &self,
+        key: &str,
+        packs: &[PackReference],
+        minecraft_version: &str,
+        datapack: Option<&[u8]>,
+        resource_pack: Option<&[u8]>,
+        conflicts: &[WeldConflict],
+    ) -> anyhow::Result<CachedPacks> {
+        std::fs::create_dir_all(&self.dir).context("Failed to create pack cache directory")?;
+
+        // Write into a temporary directory first so that a half-written entry is never used.
+        // Launches can run at the same time, so every insert gets its own directory.
+        let tmp_id = NEXT_TMP_ID.fetch_add(1, Ordering::Relaxed);
+        let tmp_dir = self
+            .dir
+            .join(format!("{TMP_PREFIX}{key}-{}-{tmp_id}", std::process::id()));
+        if tmp_dir.exists() {
+            std::fs::remove_dir_all(&tmp_dir)?;
+        }
+        std::fs::create_dir_all(&tmp_dir)?;
+        if let Some(datapack) = datapack {
+            std::fs::write(tmp_dir.join(DATAPACK_FILENAME), datapack)
+                .context("Failed to write cached datapack")?;
+        }
+        if let Some(resource_pack) = resource_pack {
+            std::fs::write(tmp_dir.join(RESOURCE_PACK_FILENAME), resource_pack)
+                .context("Failed to write cached resource pack")?;
+        }
+        let meta = CacheEntryMeta {
+            format_version: ENTRY_FORMAT_VERSION,
+            minecraft_version: minecraft_version.to_string(),
+            packs: packs.to_vec(),
+            datapack_sha1: datapack.map(hashes::sha1),
+            resource_pack_sha1: resource_pack.map(hashes::sha1),
+            conflicts: conflicts.to_vec(),
+            last_used: Utc::now().timestamp(),
+        };
+        write_meta(&tmp_dir.join(META_FILENAME), &meta)?;
+
+        let entry_dir = self.dir.join(key);
+        if entry_dir.exists() {
+            std::fs::remove_dir_all(&entry_dir).context("Failed to replace cache entry")?;
+        }
+        std::fs::rename(&tmp_dir, &entry_dir).context("Failed to move cache entry into place")?;
+
+        self.evict(Some(key))
+            .context("Failed to evict pack cache entries")?;
+
+        Ok(Self::entry_packs(&entry_dir, &meta))
+    }
+
+    /// Remove the least recently used entries until the cache fits in its size limit.
+    /// The entry with the given key is never removed.
+    pub fn evict(&self, keep: Option<&str>) -> anyhow::Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+
+        let mut entries = Vec::new();
+        let mut total_size = 0;
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Temporary entries might still be written by another insert, so they are only
+            // removed once they are old enough to have been left behind
+            if name.starts_with(TMP_PREFIX) {
+                if is_stale(&path) {
+                    let _ = std::fs::remove_dir_all(&path);
+                } else {
+                    // The entry can be moved into place while it is measured
+                    total_size += dir_size(&path).unwrap_or_default();
+                }
+                continue;
+            }
+            let size = dir_size(&path)?;
+            total_size += size;
+
+            // Broken entries are always removed first
+            let last_used = read_meta(&path.join(META_FILENAME))
+                .map(|meta| meta.last_used)
+                .unwrap_or(i64::MIN);
+            entries.push((name, path, size, last_used));
+        }
+
+        entries.sort_by_key(|(.., last_used)| *last_used);
+        for (name, path, size, _) in entries {
+            if total_size <= self.max_size {
+                break;
+            }
+            if keep == Some(name.as_str()) {
+                continue;
+            }
+            std::fs::remove_dir_all(&path)?;
+            total_size -= size;
+        }
+
+        Ok(())
+    }
+
+    /// Remove every entry from the cache
+    pub fn clear(&self) -> anyhow::Result<()> {
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir).context("Failed to remove pack cache directory")?;
+        }
+
+        Ok(())
+    }
+
+    fn entry_packs(entry_dir: &Path, meta: &CacheEntryMeta) -> CachedPacks {
+        let pack = |filename: &str, sha1: &Option<String>| {
+            sha1.as_ref().map(|sha1| CachedPack {
+                path: entry_dir.join(filename),
+                sha1: sha1.clone(),
+            })
+        };
+        CachedPacks {
+            datapack: pack(DATAPACK_FILENAME, &meta.datapack_sha1),
+            resource_pack: pack(RESOURCE_PACK_FILENAME, &meta.resource_pack_sha1),
+            conflicts: meta.conflicts.clone(),
+        }
+    }
+}
+
+fn read_meta(path: &Path) -> anyhow::Result<CacheEntryMeta> {
+    let file = File::open(path)?;
+    let out = serde_json::from_reader(BufReader::new(file))?;
+    Ok(out)
+}
+
+fn write_meta(path: &Path, meta: &CacheEntryMeta) -> anyhow::Result<()> {
+    let text = serde_json::to_string(meta)?;
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+/// Whether a leftover temporary entry hasn't been changed for long enough to remove it
+fn is_stale(path: &Path) -> bool {
+    path.metadata()
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= STALE_TMP_AGE)
+}
+
+fn dir_size(path: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += meta.len();
+        }
+    }
+    Ok(size)
+}
+
+/// The default size limit of the pack cache, in bytes
+pub const DEFAULT_MAX_CACHE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// The prefix of entries that are still being written
+const TMP_PREFIX: &str = ".tmp-";
+/// How old a temporary entry has to be before it is treated as left behind
+const STALE_TMP_AGE: Duration = Duration::from_secs(60 * 60);
+/// Counter that makes the temporary entries of one process unique
+static NEXT_TMP_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The current version of the entry format
+const ENTRY_FORMAT_VERSION: u32 = 1;
+const META_FILENAME: &str = "entry.json";
+const DATAPACK_FILENAME: &str = "datapack.zip";
+const RESOURCE_PACK_FILENAME: &str = "resourcepack.zip";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(id: &str, version: &str) -> PackReference {
+        PackReference {
+            id: id.into(),
+            version: version.into(),
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        let a = PackCache::key(&[pack("foo", "1"), pack("bar", "2")], "1.20.1");
+        assert_eq!(
+            a,
+            PackCache::key(&[pack("foo", "1"), pack("bar", "2")], "1.20.1")
+        );
+        // Reordering the packs changes which one wins conflicts
+        let b = PackCache::key(&[pack("bar", "2"), pack("foo", "1")], "1.20.1");
+        assert_ne!(a, b);
+
+        let c = PackCache::key(&[pack("foo", "1"), pack("bar", "2")], "1.20");
+        assert_ne!(a, c);
+        let d = PackCache::key(&[pack("foo", "1"), pack("bar", "3")], "1.20.1");
+        assert_ne!(a, d);
+    }
+
+    #[test]
+    fn test_insert_and_evict() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PackCache::with_dir(dir.path().to_path_buf(), 16);
+        let packs = [pack("foo", "1")];
+
+        let first = PackCache::key(&packs, "1.20");
+        let cached = cache
+            .insert(&first, &packs, "1.20", Some(&[0; 10]), None, &[])
+            .unwrap();
+        assert!(cached.datapack.is_some());
+        assert!(cached.resource_pack.is_none());
+        assert!(cache.get(&first).is_some());
+
+        // Inserting a second entry goes over the limit and evicts the first
+        let second = PackCache::key(&packs, "1.20.1");
+        cache
+            .insert(&second, &packs, "1.20.1", Some(&[0; 10]), None, &[])
+            .unwrap();
+        assert!(cache.get(&first).is_none());
+        assert!(cache.get(&second).is_some());
+
+        cache.clear().unwrap();
+        assert!(cache.get(&second).is_none());
+    }
+
+    #[test]
+    fn test_old_entries_are_misses() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PackCache::with_dir(dir.path().to_path_buf(), u64::MAX);
+        let packs = [pack("foo", "1")];
+        let key = PackCache::key(&packs, "1.20");
+        cache
+            .insert(&key, &packs, "1.20", Some(&[0; 10]), None, &[])
+            .unwrap();
+
+        // Entries written before hashes were stored
+        let entry_dir = dir.path().join(&key);
+        let meta = serde_json::json!({
+            "minecraft_version": "1.20",
+            "packs": packs,
+            "last_used": 0,
+        });
+        std::fs::write(entry_dir.join(META_FILENAME), meta.to_string()).unwrap();
+        assert!(cache.get(&key).is_none());
+        assert!(!entry_dir.exists());
+    }
+
+    #[test]
+    fn test_evict_keeps_tmp_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PackCache::with_dir(dir.path().to_path_buf(), 0);
+        // An entry that another insert is still writing
+        let tmp_dir = dir.path().join(format!("{TMP_PREFIX}foo-1-0"));
+        std::fs::create_dir_all(&tmp_dir).unwrap();
+        std::fs::write(tmp_dir.join(DATAPACK_FILENAME), [0; 10]).unwrap();
+        let broken = dir.path().join("broken");
+        std::fs::create_dir_all(&broken).unwrap();
+        std::fs::write(broken.join(DATAPACK_FILENAME), [0; 10]).unwrap();
+
+        cache.evict(None).unwrap();
+        assert!(tmp_dir.exists());
+        assert!(!broken.exists());
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use directories::ProjectDirs;
+
+/// Get the directories that the launcher stores all of its files in
+pub fn project_dirs() -> anyhow::Result<ProjectDirs> {
+    ProjectDirs::from("dev.smithed", "Smithed", "smithed_launcher")
+        .ok_or(anyhow!("Failed to create project directories"))
+}
+
+/// The launcher config with the local bundles
+pub fn config_file(dirs: &ProjectDirs) -> PathBuf {
+    dirs.config_dir().join(CONFIG_FILENAME)
+}
+
+/// The Microsoft accounts that have been added
+pub fn users_file(dirs: &ProjectDirs) -> PathBuf {
+    dirs.config_dir().join(USERS_FILENAME)
+}
+
+/// The key that account tokens are encrypted with
+pub fn token_key_file(dirs: &ProjectDirs) -> PathBuf {
+    dirs.data_local_dir().join("account_tokens.key")
+}
+
+/// A mod manifest that the user placed to override the bundled one
+pub fn mod_manifest_override_file(dirs: &ProjectDirs) -> PathBuf {
+    dirs.config_dir().join("mod_manifest.json")
+}
+
+/// The last mod manifest that was fetched
+pub fn cached_mod_manifest_file(dirs: &ProjectDirs) -> PathBuf {
+    dirs.cache_dir().join("mod_manifest.json")
+}
+
+/// The directory of cached API responses
+pub fn api_cache_dir(dirs: &ProjectDirs) -> PathBuf {
+    dirs.cache_dir().join("api")
+}
+
+/// The directory of the pack cache
+pub fn pack_cache_dir(dirs: &ProjectDirs) -> PathBuf {
+    dirs.cache_dir().join("packs")
+}
+
+/// Move config files that old versions wrote to other directories into the config directory
+pub fn migrate_legacy_files(dirs: &ProjectDirs) -> anyhow::Result<()> {
+    let legacy_dirs: Vec<_> = LEGACY_PROJECT_DIRS
+        .iter()
+        .filter_map(|(qualifier, organization, application)| {
+            ProjectDirs::from(qualifier, organization, application)
+        })
+        .map(|dirs| dirs.config_dir().to_path_buf())
+        .collect();
+
+    migrate_files(&legacy_dirs, dirs.config_dir())
+}
+
+/// Move the stray config files from the legacy directories. Files that already exist
+/// in the config directory are kept, and the first legacy directory that has a file wins.
+fn migrate_files(legacy_dirs: &[PathBuf], config_dir: &Path) -> anyhow::Result<()> {
+    for filename in [CONFIG_FILENAME, USERS_FILENAME] {
+        let target = config_dir.join(filename);
+        for legacy_dir in legacy_dirs {
+            // Some platforms put several of the old directories in the same place
+            if legacy_dir == config_dir {
+                continue;
+            }
+            let source = legacy_dir.join(filename);
+            if !source.exists() {
+                continue;
+            }
+            if target.exists() {
+                println!(
+                    "Not migrating {} because {} already exists",
+                    source.display(),
+                    target.display()
+                );
+                continue;
+            }
+
+            std::fs::create_dir_all(config_dir).context("Failed to create config directory")?;
+            move_file(&source, &target)
+                .with_context(|| format!("Failed to migrate {}", source.display()))?;
+            println!("Migrated {} to {}", source.display(), target.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Move a file, copying it if it can't be renamed, like when it is on another filesystem
+fn move_file(source: &Path, target: &Path) -> anyhow::Result<()> {
+    if std::fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(source, target).context("Failed to copy file")?;
+    std::fs::remove_file(source).context("Failed to remove old file")?;
+
+    Ok(())
+}
+
+const CONFIG_FILENAME: &str = "smithed.json";
+const USERS_FILENAME: &str = "users.json";
+/// Project directories that old versions wrote config files to
+const LEGACY_PROJECT_DIRS: [(&str, &str, &str); 2] = [
+    ("", "smithed", "smithed_launcher"),
+    ("", "smithed", "launcher"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_dir = dir.path().join("config");
+        let legacy_a = dir.path().join("a");
+        let legacy_b = dir.path().join("b");
+        std::fs::create_dir_all(&legacy_a).unwrap();
+        std::fs::create_dir_all(&legacy_b).unwrap();
+        std::fs::write(legacy_a.join(CONFIG_FILENAME), "a").unwrap();
+        std::fs::write(legacy_b.join(CONFIG_FILENAME), "b").unwrap();
+        std::fs::write(legacy_b.join(USERS_FILENAME), "users").unwrap();
+
+        migrate_files(&[legacy_a.clone(), legacy_b.clone()], &config_dir).unwrap();
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(config_dir.join(CONFIG_FILENAME)), "a");
+        assert_eq!(read(config_dir.join(USERS_FILENAME)), "users");
+        assert!(!legacy_a.join(CONFIG_FILENAME).exists());
+        assert!(!legacy_b.join(USERS_FILENAME).exists());
+        // The config that lost is left alone instead of overwriting the migrated one
+        assert_eq!(read(legacy_b.join(CONFIG_FILENAME)), "b");
+
+        // Running it again does nothing
+        migrate_files(&[legacy_a, legacy_b], &config_dir).unwrap();
+        assert_eq!(read(config_dir.join(CONFIG_FILENAME)), "a");
+    }
+}
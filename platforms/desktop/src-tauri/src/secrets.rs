@@ -0,0 +1,135 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::files::restrict_permissions;
+
+/// Key used to encrypt secrets, like account tokens, that are stored on disk
+pub struct SecretKey {
+    cipher: ChaCha20Poly1305,
+}
+
+/// An encrypted secret that can be stored in a config file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EncryptedSecret {
+    /// Hex encoded nonce
+    pub nonce: String,
+    /// Hex encoded ciphertext
+    pub data: String,
+}
+
+impl SecretKey {
+    /// Load the key from a key file, creating a new random key if it doesn't exist.
+    /// The key file is only readable by the current user.
+    pub fn load_or_create(path: &Path) -> anyhow::Result<Self> {
+        if path.exists() {
+            restrict_permissions(path)?;
+            let bytes = std::fs::read(path).context("Failed to read key file")?;
+            if bytes.len() != KEY_LENGTH {
+                bail!("Key file has an invalid length");
+            }
+            return Ok(Self::from_bytes(&bytes));
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create key directory")?;
+        }
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path).context("Failed to create key file")?;
+        file.write_all(&key).context("Failed to write key file")?;
+        file.sync_all().context("Failed to write key file")?;
+        restrict_permissions(path)?;
+
+        Ok(Self::from_bytes(&key))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(bytes)),
+        }
+    }
+
+    /// Encrypt data with a new random nonce
+    pub fn encrypt(&self, plaintext: &[u8]) -> anyhow::Result<EncryptedSecret> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let data = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+
+        Ok(EncryptedSecret {
+            nonce: hex::encode(nonce),
+            data: hex::encode(data),
+        })
+    }
+
+    /// Decrypt data. Fails if the data was encrypted with a different key or was changed.
+    pub fn decrypt(&self, secret: &EncryptedSecret) -> anyhow::Result<Vec<u8>> {
+        let nonce = hex::decode(&secret.nonce).context("Invalid nonce")?;
+        if nonce.len() != NONCE_LENGTH {
+            bail!("Invalid nonce length");
+        }
+        let data = hex::decode(&secret.data).context("Invalid secret data")?;
+        self.cipher
+            .decrypt(Nonce::from_slice(&nonce), data.as_slice())
+            .map_err(|_| anyhow!("Failed to decrypt secret"))
+    }
+
+    /// Serialize and encrypt a value
+    pub fn encrypt_json<T: Serialize>(&self, value: &T) -> anyhow::Result<EncryptedSecret> {
+        let data = serde_json::to_vec(value).context("Failed to serialize secret")?;
+        self.encrypt(&data)
+    }
+
+    /// Decrypt and deserialize a value
+    pub fn decrypt_json<T: DeserializeOwned>(&self, secret: &EncryptedSecret) -> anyhow::Result<T> {
+        let data = self.decrypt(secret)?;
+        serde_json::from_slice(&data).context("Failed to deserialize secret")
+    }
+}
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.key");
+        let key = SecretKey::load_or_create(&path).unwrap();
+        let secret = key.encrypt_json(&"token".to_string()).unwrap();
+        assert!(!secret.data.contains(&hex::encode("token")));
+
+        // Loading the key again has to give the same key
+        let key = SecretKey::load_or_create(&path).unwrap();
+        assert_eq!(key.decrypt_json::<String>(&secret).unwrap(), "token");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let mut tampered = secret.clone();
+        let last = tampered.data.pop().unwrap();
+        tampered.data.push(if last == '0' { '1' } else { '0' });
+        assert!(key.decrypt(&tampered).is_err());
+    }
+}
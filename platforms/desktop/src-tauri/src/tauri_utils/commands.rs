@@ -1,120 +1,321 @@
 use std::collections::HashMap;
-use std::fmt::Debug;
+use std::time::Duration;
 
 use directories::ProjectDirs;
+use mcvm::core::user::{User, UserKind};
 use mcvm::shared::output::{MCVMOutput, MessageContents, MessageLevel};
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use tauri::{async_runtime, Manager};
 
-use crate::api;
+use crate::api::{self, ApiClient};
 use crate::api_types::{PackBundle, PackData, PackReference};
-use crate::config::{LocalBundleConfig, SmithedConfig};
-use crate::mcvm::output::SmithedMCVMOutput;
-use crate::minecraft::launch::launch_bundle;
-
+use crate::config::{ConfigStore, LocalBundleConfig};
+use crate::dependencies::{resolve_dependencies, DependencyResolution};
+use crate::mcvm::output::{set_game_status, SmithedMCVMOutput};
+use crate::mcvm::users::{AccountInfo, UserStore};
+use crate::minecraft::auth::microsoft;
+use crate::minecraft::launch::{launch_bundle, GameProcess, ModLoader, ModManifest, StopOutcome};
+use crate::pack_cache::PackCache;
+use crate::updates::{check_update, PackUpdate};
+
+use super::error::CommandError;
+use super::status::GameStatus;
 use super::{LaunchedGame, SmithedState};
 
 #[tauri::command]
 pub async fn launch_game(
     app_handle: tauri::AppHandle,
-    mut state: tauri::State<'_, SmithedState>,
+    state: tauri::State<'_, SmithedState>,
     bundle_id: String,
     offline: bool,
-) -> Result<(), String> {
-    let output = SmithedMCVMOutput::new(app_handle);
-    let bundle = get_bundle_impl(&bundle_id, &state.project_dirs).await?;
-
-    // Make sure the game is stopped first
-    stop_game_impl(&mut state)?;
-
-    let lock = state.launched_game.lock();
-    let mut lock = fmt_err(lock)?;
-    *lock = Some(get_launched_game(
-        bundle_id,
+) -> Result<(), CommandError> {
+    let bundle = get_bundle_impl(&bundle_id, &state.config).await?;
+    let user = get_launch_user(&state.project_dirs, &state.client, offline).await?;
+    let config = state.config.get().await.map_err(CommandError::config)?;
+    let grace_period = Duration::from_secs(config.stop_grace_period);
+
+    let output = SmithedMCVMOutput::for_bundle(app_handle, bundle_id.clone());
+
+    // Replacing the game under one lock means that launching the same bundle twice at once
+    // can't start two games. Games for other bundles keep running.
+    let mut lock = state
+        .launched_games
+        .lock()
+        .map_err(CommandError::internal)?;
+    let previous = lock.remove(&bundle_id);
+    let game = get_launched_game(
+        bundle_id.clone(),
         bundle,
         offline,
-        &state.client,
+        user,
+        previous.map(|game| (game, grace_period)),
+        &state.api,
+        &state.pack_cache,
+        &state.project_dirs,
         output,
-    ));
+    );
+    lock.insert(bundle_id, game);
 
     Ok(())
 }
 
+/// Get the user to launch the game with from the selected account.
+/// Expired tokens are refreshed and stored again before launching online.
+async fn get_launch_user(
+    project_dirs: &ProjectDirs,
+    client: &Client,
+    offline: bool,
+) -> Result<User, CommandError> {
+    let mut store = UserStore::open(project_dirs).map_err(CommandError::config)?;
+    let Some(account) = store.get_selected().cloned() else {
+        // Playing offline doesn't need an account
+        if offline {
+            return Ok(User::new(
+                UserKind::Unverified,
+                "smithed-user",
+                "SmithedUser",
+            ));
+        }
+        return Err(CommandError::NoAccountSelected);
+    };
+    if offline {
+        return Ok(account.to_user(true, None));
+    }
+
+    let login_required = || CommandError::LoginRequired {
+        account_id: account.id.clone(),
+    };
+    let key = UserStore::open_key(project_dirs).map_err(CommandError::config)?;
+    // Tokens that can't be decrypted, like ones from another install, need a new login
+    let tokens = store
+        .get_tokens(&account.id, &key)
+        .ok()
+        .flatten()
+        .ok_or_else(login_required)?;
+    if !tokens.is_expired() {
+        return Ok(account.to_user(false, Some(&tokens)));
+    }
+
+    let refresh_token = tokens.refresh_token.as_ref().ok_or_else(login_required)?;
+    let tokens = microsoft::refresh(client, refresh_token)
+        .await
+        .map_err(|e| {
+            // A revoked or expired refresh token can only be fixed by logging in again
+            if e.chain().any(|x| x.is::<microsoft::RefreshTokenRejected>()) {
+                login_required()
+            } else {
+                CommandError::api(e)
+            }
+        })?;
+    store
+        .set_tokens(&account.id, &tokens, &key)
+        .map_err(CommandError::internal)?;
+    store.write(project_dirs).map_err(CommandError::config)?;
+
+    Ok(account.to_user(false, Some(&tokens)))
+}
+
+/// Start the task that launches a bundle's game. A previous game for the bundle is stopped
+/// with its grace period before the new one is launched.
+#[allow(clippy::too_many_arguments)]
 fn get_launched_game(
     bundle_id: String,
     bundle: LocalBundleConfig,
     offline: bool,
-    client: &Client,
+    user: User,
+    previous: Option<(LaunchedGame, Duration)>,
+    api: &ApiClient,
+    pack_cache: &PackCache,
+    project_dirs: &ProjectDirs,
     o: SmithedMCVMOutput,
 ) -> LaunchedGame {
     println!("Launching game!");
-    let client = client.clone();
+    let api = api.clone();
+    let pack_cache = pack_cache.clone();
+    let project_dirs = project_dirs.clone();
+    let process = GameProcess::default();
+    let task_process = process.clone();
     let task_handle = async_runtime::spawn(async move {
         let mut o = o;
-        let res = launch_bundle(bundle_id, bundle, offline, &client, &mut o).await;
+        if let Some((previous, grace_period)) = previous {
+            if let Err(e) = previous.stop(grace_period).await {
+                eprintln!("Failed to stop the previous game: {e:?}");
+            }
+        }
+        let res = launch_bundle(
+            bundle_id.clone(),
+            bundle,
+            offline,
+            user,
+            &api,
+            &pack_cache,
+            &project_dirs,
+            &task_process,
+            &mut o,
+        )
+        .await;
+        remove_launched_game(o.app_handle(), &bundle_id, &task_process);
         if let Err(e) = res {
             o.display(
                 MessageContents::Error(format!("{e:?}")),
                 MessageLevel::Important,
             );
-            return Err(e);
+            let error = CommandError::launch(e);
+            o.set_status(GameStatus::Failed {
+                error: error.clone(),
+            });
+            return Err(error.into());
         }
         println!("Game closed");
         let app = o.get_app_handle();
-        app.emit_all("game_finished", ())?;
+        app.emit_all("game_finished", bundle_id)?;
         Ok(())
     });
-    LaunchedGame { task_handle }
+    LaunchedGame {
+        task_handle,
+        process,
+    }
 }
 
+/// Remove a bundle's game once its launch task has finished, unless it was already
+/// replaced by another launch
+fn remove_launched_game(app: &tauri::AppHandle, bundle_id: &str, process: &GameProcess) {
+    let state = app.state::<SmithedState>();
+    let Ok(mut lock) = state.launched_games.lock() else {
+        return;
+    };
+    if lock
+        .get(bundle_id)
+        .is_some_and(|game| game.process.is_same(process))
+    {
+        lock.remove(bundle_id);
+    }
+}
+
+/// Stop the game of a bundle, giving it a grace period to exit before it is killed
 #[tauri::command]
-pub async fn stop_game(mut state: tauri::State<'_, SmithedState>) -> Result<(), String> {
-    println!("Stopping game...");
-    stop_game_impl(&mut state)?;
+pub async fn stop_game(
+    app_handle: tauri::AppHandle,
+    mut state: tauri::State<'_, SmithedState>,
+    bundle_id: String,
+) -> Result<StopOutcome, CommandError> {
+    println!("Stopping game for bundle '{bundle_id}'...");
+    stop_game_impl(&app_handle, &mut state, &bundle_id).await
+}
 
-    Ok(())
+async fn stop_game_impl(
+    app_handle: &tauri::AppHandle,
+    state: &mut tauri::State<'_, SmithedState>,
+    bundle_id: &str,
+) -> Result<StopOutcome, CommandError> {
+    let game = {
+        let mut lock = state
+            .launched_games
+            .lock()
+            .map_err(CommandError::internal)?;
+        lock.remove(bundle_id)
+    };
+    let Some(game) = game else {
+        return Ok(StopOutcome::not_running());
+    };
+
+    let config = state.config.get().await.map_err(CommandError::config)?;
+    let grace_period = Duration::from_secs(config.stop_grace_period);
+    let process = game.process.clone();
+    let outcome = game
+        .stop(grace_period)
+        .await
+        .map_err(CommandError::internal)?;
+    // The launch task was stopped too, so the exit status is set here instead
+    let status = if outcome.was_running && outcome.exited {
+        let code = process.wait().await.map_err(CommandError::internal)?;
+        GameStatus::Exited { code }
+    } else {
+        GameStatus::Idle
+    };
+    set_game_status(app_handle, bundle_id, status);
+
+    Ok(outcome)
+}
+
+/// Get the current status of a bundle's game
+#[tauri::command]
+pub async fn get_game_status(
+    state: tauri::State<'_, SmithedState>,
+    bundle_id: String,
+) -> Result<GameStatus, CommandError> {
+    let lock = state.game_statuses.lock().map_err(CommandError::internal)?;
+
+    Ok(lock.get(&bundle_id).cloned().unwrap_or_default())
 }
 
-fn stop_game_impl(state: &mut tauri::State<'_, SmithedState>) -> Result<(), String> {
-    let lock = state.launched_game.lock();
-    let mut lock = fmt_err(lock)?;
-    lock.as_mut().map(|game| game.task_handle.abort());
-    lock.take();
+/// Clear the final status of a bundle's game once it has exited or failed. The status
+/// is kept until then so that it can still be shown after the frontend reloads.
+#[tauri::command]
+pub async fn clear_game_status(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SmithedState>,
+    bundle_id: String,
+) -> Result<(), CommandError> {
+    let running = state
+        .launched_games
+        .lock()
+        .map_err(CommandError::internal)?
+        .contains_key(&bundle_id);
+    if !running {
+        set_game_status(&app_handle, &bundle_id, GameStatus::Idle);
+    }
 
     Ok(())
 }
 
+/// Get the status of every bundle's game that isn't idle
+#[tauri::command]
+pub async fn get_game_statuses(
+    state: tauri::State<'_, SmithedState>,
+) -> Result<HashMap<String, GameStatus>, CommandError> {
+    let lock = state.game_statuses.lock().map_err(CommandError::internal)?;
+
+    Ok(lock.clone())
+}
+
 #[tauri::command]
 pub async fn add_bundle(
     bundle_id: String,
     bundle: LocalBundleConfig,
     state: tauri::State<'_, SmithedState>,
-) -> Result<(), String> {
-    let mut config = fmt_err(SmithedConfig::open(&state.project_dirs))?;
-    config.local_bundles.insert(bundle_id, bundle);
-    fmt_err(config.write(&state.project_dirs))?;
-
-    Ok(())
+) -> Result<(), CommandError> {
+    state
+        .config
+        .update(|config| {
+            config.local_bundles.insert(bundle_id, bundle);
+            Ok(())
+        })
+        .await
+        .map_err(CommandError::config)
 }
 
 #[tauri::command]
 pub async fn get_bundle(
     bundle_id: String,
     state: tauri::State<'_, SmithedState>,
-) -> Result<LocalBundleConfig, String> {
-    get_bundle_impl(&bundle_id, &state.project_dirs).await
+) -> Result<LocalBundleConfig, CommandError> {
+    get_bundle_impl(&bundle_id, &state.config).await
 }
 
 async fn get_bundle_impl(
     bundle_id: &str,
-    project_dirs: &ProjectDirs,
-) -> Result<LocalBundleConfig, String> {
-    let config = fmt_err(SmithedConfig::open(project_dirs))?;
-    let bundle = config
-        .local_bundles
-        .get(bundle_id)
-        .ok_or("Bundle does not exist".to_string())?;
+    config: &ConfigStore,
+) -> Result<LocalBundleConfig, CommandError> {
+    let config = config.get().await.map_err(CommandError::config)?;
+    let bundle =
+        config
+            .local_bundles
+            .get(bundle_id)
+            .ok_or_else(|| CommandError::BundleNotFound {
+                bundle_id: bundle_id.to_string(),
+            })?;
 
     Ok(bundle.clone())
 }
@@ -122,8 +323,8 @@ async fn get_bundle_impl(
 #[tauri::command]
 pub async fn list_bundles(
     state: tauri::State<'_, SmithedState>,
-) -> Result<HashMap<String, LocalBundleConfig>, String> {
-    let config = fmt_err(SmithedConfig::open(&state.project_dirs))?;
+) -> Result<HashMap<String, LocalBundleConfig>, CommandError> {
+    let config = state.config.get().await.map_err(CommandError::config)?;
 
     Ok(config.local_bundles)
 }
@@ -132,8 +333,8 @@ pub async fn list_bundles(
 pub async fn bundle_exists(
     bundle_id: String,
     state: tauri::State<'_, SmithedState>,
-) -> Result<bool, String> {
-    let config = fmt_err(SmithedConfig::open(&state.project_dirs))?;
+) -> Result<bool, CommandError> {
+    let config = state.config.get().await.map_err(CommandError::config)?;
 
     Ok(config.local_bundles.contains_key(&bundle_id))
 }
@@ -142,38 +343,64 @@ pub async fn bundle_exists(
 pub async fn remove_bundle(
     bundle_id: String,
     state: tauri::State<'_, SmithedState>,
-) -> Result<(), String> {
-    let mut config = fmt_err(SmithedConfig::open(&state.project_dirs))?;
-    if config.local_bundles.contains_key(&bundle_id) {
-        config.local_bundles.remove(&bundle_id);
-    } else {
-        return Err("Bundle does not exist".into());
-    }
-    fmt_err(config.write(&state.project_dirs))?;
-
-    Ok(())
+) -> Result<(), CommandError> {
+    state
+        .config
+        .update(|config| {
+            if config.local_bundles.remove(&bundle_id).is_none() {
+                return Err(CommandError::BundleNotFound { bundle_id }.into());
+            }
+            Ok(())
+        })
+        .await
+        .map_err(CommandError::config)
 }
 
+/// Add a pack and all of its dependencies to a bundle. If there are any conflicts, nothing
+/// is added and they are returned as an error.
 #[tauri::command]
 pub async fn add_pack_to_bundle(
     bundle_id: String,
     pack: PackReference,
     state: tauri::State<'_, SmithedState>,
-) -> Result<(), String> {
+) -> Result<DependencyResolution, CommandError> {
     println!(
         "Adding {} of version {} to bundle {bundle_id}",
         pack.id, pack.version
     );
-    let mut config = fmt_err(SmithedConfig::open(&state.project_dirs))?;
-    if let Some(bundle) = config.local_bundles.get_mut(&bundle_id) {
-        bundle.packs.push(pack);
-    } else {
-        return Err("Bundle does not exist".into());
+    let bundle = get_bundle_impl(&bundle_id, &state.config).await?;
+    let api = state.api.clone();
+    let pack_id = pack.id.clone();
+    let resolution = resolve_dependencies(pack, &bundle.version, &bundle.packs, |id| {
+        let api = api.clone();
+        async move { api::get_pack(&api, &id).await }
+    })
+    .await;
+    let resolution = resolution.map_err(CommandError::api)?;
+    if !resolution.conflicts.is_empty() {
+        return Err(CommandError::DependencyConflicts {
+            pack_id,
+            conflicts: resolution.conflicts,
+        });
     }
 
-    fmt_err(config.write(&state.project_dirs))?;
-
-    Ok(())
+    state
+        .config
+        .update(|config| {
+            let Some(bundle) = config.local_bundles.get_mut(&bundle_id) else {
+                return Err(CommandError::BundleNotFound { bundle_id }.into());
+            };
+            for pack in &resolution.added {
+                if !bundle.packs.iter().any(|x| x.id == pack.id) {
+                    bundle.packs.push(pack.clone());
+                }
+            }
+            Ok(())
+        })
+        .await
+        .map_err(CommandError::config)?;
+
+    Ok(resolution)
 }
 
 #[tauri::command]
@@ -181,22 +408,23 @@ pub async fn remove_pack_from_bundle(
     bundle_id: String,
     pack_id: String,
     state: tauri::State<'_, SmithedState>,
-) -> Result<(), String> {
-    let mut config = fmt_err(SmithedConfig::open(&state.project_dirs))?;
-    if let Some(bundle) = config.local_bundles.get_mut(&bundle_id) {
-        let index = bundle.packs.iter().position(|x| x.id == pack_id.as_str());
-        if let Some(index) = index {
-            bundle.packs.remove(index);
-        } else {
-            println!("Did not remove pack from bundle as it is not in the bundle");
-        }
-    } else {
-        return Err("Bundle does not exist".into());
-    }
-
-    fmt_err(config.write(&state.project_dirs))?;
-
-    Ok(())
+) -> Result<(), CommandError> {
+    state
+        .config
+        .update(|config| {
+            let Some(bundle) = config.local_bundles.get_mut(&bundle_id) else {
+                return Err(CommandError::BundleNotFound { bundle_id }.into());
+            };
+            let index = bundle.packs.iter().position(|x| x.id == pack_id.as_str());
+            if let Some(index) = index {
+                bundle.packs.remove(index);
+            } else {
+                println!("Did not remove pack from bundle as it is not in the bundle");
+            }
+            Ok(())
+        })
+        .await
+        .map_err(CommandError::config)
 }
 
 #[tauri::command]
@@ -204,29 +432,140 @@ pub async fn get_pack_version_for_bundle(
     bundle_id: String,
     pack_id: String,
     state: tauri::State<'_, SmithedState>,
-) -> Result<Option<String>, String> {
-    let bundle = get_bundle_impl(&bundle_id, &state.project_dirs).await?;
-    let pack = fmt_err(api::get_pack(&state.client, &pack_id).await)?;
+) -> Result<Option<String>, CommandError> {
+    let bundle = get_bundle_impl(&bundle_id, &state.config).await?;
+    let pack = api::get_pack(&state.api, &pack_id)
+        .await
+        .map_err(CommandError::api)?;
     let version = pack.get_newest_version(&bundle.version);
 
     Ok(version.map(|x| x.name.clone()))
 }
 
-/// Get the packs in a bundle. Returns a list of tuples with the pack reference and data.
+/// The update check for a pack in a bundle, or the error from getting the pack
+#[derive(Serialize, Clone)]
+pub struct PackUpdateCheck {
+    pub id: String,
+    pub update: Option<PackUpdate>,
+    pub error: Option<CommandError>,
+}
+
+/// Check every pack in a bundle for a newer version that supports the bundle's Minecraft version.
+/// The packs are fetched at the same time, and a pack that can't be fetched has an error
+/// instead of stopping the check for the rest of the packs.
 #[tauri::command]
-pub async fn get_bundle_packs(
+pub async fn check_bundle_updates(
+    bundle_id: String,
+    state: tauri::State<'_, SmithedState>,
+) -> Result<Vec<PackUpdateCheck>, CommandError> {
+    let bundle = get_bundle_impl(&bundle_id, &state.config).await?;
+    let ids: Vec<_> = bundle.packs.iter().map(|pack| pack.id.clone()).collect();
+    let results = api::get_packs(&state.api, &ids).await;
+    let out = bundle
+        .packs
+        .iter()
+        .zip(results)
+        .map(|(pack, result)| match result {
+            Ok(data) => PackUpdateCheck {
+                id: pack.id.clone(),
+                update: Some(check_update(pack, &data, &bundle.version)),
+                error: None,
+            },
+            Err(e) => PackUpdateCheck {
+                id: pack.id.clone(),
+                update: None,
+                error: Some(CommandError::api(e)),
+            },
+        })
+        .collect();
+
+    Ok(out)
+}
+
+/// Update the given packs in a bundle to their newest versions. Either every pack is
+/// updated or none are. Returns the updates that were applied.
+#[tauri::command]
+pub async fn update_bundle_packs(
     bundle_id: String,
+    pack_ids: Vec<String>,
     state: tauri::State<'_, SmithedState>,
-) -> Result<Vec<(PackReference, PackData)>, String> {
-    let bundle = get_bundle_impl(&bundle_id, &state.project_dirs).await?;
-    let mut out_packs = Vec::new();
-    for pack in bundle.packs {
-        let pack_data = api::get_pack(&state.client, &pack.id).await;
-        let pack_data = fmt_err(pack_data)?;
-        out_packs.push((pack, pack_data));
+) -> Result<Vec<PackUpdate>, CommandError> {
+    let bundle = get_bundle_impl(&bundle_id, &state.config).await?;
+    // Find all of the new versions before touching the config
+    let mut updates = Vec::new();
+    for pack_id in &pack_ids {
+        let Some(pack) = bundle.packs.iter().find(|x| &x.id == pack_id) else {
+            return Err(CommandError::PackNotInBundle {
+                bundle_id,
+                pack_id: pack_id.clone(),
+            });
+        };
+        let pack_data = api::get_pack(&state.api, pack_id)
+            .await
+            .map_err(CommandError::api)?;
+        let update = check_update(pack, &pack_data, &bundle.version);
+        if update.update_available {
+            updates.push(update);
+        }
     }
 
-    Ok(out_packs)
+    state
+        .config
+        .update(|config| {
+            let Some(bundle) = config.local_bundles.get_mut(&bundle_id) else {
+                return Err(CommandError::BundleNotFound { bundle_id }.into());
+            };
+            for update in &updates {
+                let pack = bundle.packs.iter_mut().find(|x| x.id == update.id);
+                if let (Some(pack), Some(newest_version)) = (pack, &update.newest_version) {
+                    pack.version = newest_version.clone();
+                }
+            }
+            Ok(())
+        })
+        .await
+        .map_err(CommandError::config)?;
+
+    Ok(updates)
+}
+
+/// A pack in a bundle with its data from the API, or the error from getting it
+#[derive(Serialize, Clone)]
+pub struct BundlePack {
+    pub reference: PackReference,
+    pub data: Option<PackData>,
+    pub error: Option<CommandError>,
+}
+
+/// Get the packs in a bundle in the same order as the bundle. Packs that couldn't be
+/// fetched have an error instead of data, so that the rest of the packs can still be shown.
+#[tauri::command]
+pub async fn get_bundle_packs(
+    bundle_id: String,
+    state: tauri::State<'_, SmithedState>,
+) -> Result<Vec<BundlePack>, CommandError> {
+    let bundle = get_bundle_impl(&bundle_id, &state.config).await?;
+    let ids: Vec<_> = bundle.packs.iter().map(|pack| pack.id.clone()).collect();
+    let results = api::get_packs(&state.api, &ids).await;
+    let out = bundle
+        .packs
+        .into_iter()
+        .zip(results)
+        .map(|(reference, result)| match result {
+            Ok(data) => BundlePack {
+                reference,
+                data: Some(data),
+                error: None,
+            },
+            Err(e) => BundlePack {
+                reference,
+                data: None,
+                error: Some(CommandError::api(e)),
+            },
+        })
+        .collect();
+
+    Ok(out)
 }
 
 /// Get a remote bundle
@@ -234,8 +573,10 @@ pub async fn get_bundle_packs(
 pub async fn get_remote_bundle(
     bundle_id: String,
     state: tauri::State<'_, SmithedState>,
-) -> Result<PackBundle, String> {
-    let bundle = fmt_err(api::get_bundle(&state.client, &bundle_id).await)?;
+) -> Result<PackBundle, CommandError> {
+    let bundle = api::get_bundle(&state.api, &bundle_id)
+        .await
+        .map_err(CommandError::api)?;
     Ok(bundle)
 }
 
@@ -245,8 +586,15 @@ pub async fn import_bundle(
     bundle_id: String,
     local_bundle_id: String,
     state: tauri::State<'_, SmithedState>,
-) -> Result<(), String> {
-    let bundle = fmt_err(api::get_bundle(&state.client, &bundle_id).await)?;
+) -> Result<(), CommandError> {
+    let bundle = api::get_bundle(&state.api, &bundle_id)
+        .await
+        .map_err(CommandError::api)?;
+    let manifest = ModManifest::load_offline(&state.project_dirs).map_err(CommandError::config)?;
+    manifest
+        .require_mods(&bundle.version, ModLoader::Fabric)
+        .map_err(|e| CommandError::UnsupportedVersion { version: e.version })?;
+
     let bundle = LocalBundleConfig {
         version: bundle.version,
         packs: bundle.packs,
@@ -254,13 +602,74 @@ pub async fn import_bundle(
     add_bundle(local_bundle_id, bundle, state).await
 }
 
-/// Errors for importing a bundle
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum ImportBundleError {
-    UnsupportedVersion,
+/// Remove all of the welded packs from the pack cache
+#[tauri::command]
+pub async fn clear_pack_cache(state: tauri::State<'_, SmithedState>) -> Result<(), CommandError> {
+    state.pack_cache.clear().map_err(CommandError::internal)
+}
+
+/// List the Microsoft accounts that have been added
+#[tauri::command]
+pub async fn list_accounts(
+    state: tauri::State<'_, SmithedState>,
+) -> Result<Vec<AccountInfo>, CommandError> {
+    let store = UserStore::open(&state.project_dirs).map_err(CommandError::config)?;
+
+    Ok(store.list())
+}
+
+/// Add a Microsoft account by logging in with a device code.
+/// The code is sent to the frontend with the mcvm_display_auth_info event.
+#[tauri::command]
+pub async fn add_account(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SmithedState>,
+) -> Result<AccountInfo, CommandError> {
+    let mut output = SmithedMCVMOutput::new(app_handle);
+    let login = microsoft::login(&state.client, &mut output)
+        .await
+        .map_err(CommandError::api)?;
+
+    let mut store = UserStore::open(&state.project_dirs).map_err(CommandError::config)?;
+    let key = UserStore::open_key(&state.project_dirs).map_err(CommandError::config)?;
+    let account = store.add(&login, &key).map_err(CommandError::internal)?;
+    store
+        .write(&state.project_dirs)
+        .map_err(CommandError::config)?;
+
+    Ok(account)
+}
+
+/// Remove a Microsoft account
+#[tauri::command]
+pub async fn remove_account(
+    account_id: String,
+    state: tauri::State<'_, SmithedState>,
+) -> Result<(), CommandError> {
+    let mut store = UserStore::open(&state.project_dirs).map_err(CommandError::config)?;
+    if !store.remove(&account_id) {
+        return Err(CommandError::AccountNotFound { account_id });
+    }
+    store
+        .write(&state.project_dirs)
+        .map_err(CommandError::config)?;
+
+    Ok(())
 }
 
-fn fmt_err<T, E: Debug>(r: Result<T, E>) -> Result<T, String> {
-    r.map_err(|x| format!("{x:?}"))
+/// Select the Microsoft account to launch the game with
+#[tauri::command]
+pub async fn select_account(
+    account_id: String,
+    state: tauri::State<'_, SmithedState>,
+) -> Result<(), CommandError> {
+    let mut store = UserStore::open(&state.project_dirs).map_err(CommandError::config)?;
+    if !store.select(&account_id) {
+        return Err(CommandError::AccountNotFound { account_id });
+    }
+    store
+        .write(&state.project_dirs)
+        .map_err(CommandError::config)?;
+
+    Ok(())
 }
@@ -0,0 +1,217 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dependencies::DependencyConflict;
+use crate::hashes::HashMismatchError;
+use crate::minecraft::launch::{MissingArtifactsError, UnsupportedVersionError};
+
+/// Error returned from Tauri commands. The kind is serialized as a stable `kind` field
+/// so that the frontend can tell different failures apart.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CommandError {
+    /// The local bundle does not exist
+    BundleNotFound { bundle_id: String },
+    /// The pack is not in the local bundle
+    PackNotInBundle { bundle_id: String, pack_id: String },
+    /// The pack can't be added to the bundle because its dependencies conflict
+    DependencyConflicts {
+        pack_id: String,
+        conflicts: Vec<DependencyConflict>,
+    },
+    /// The Microsoft account has not been added
+    AccountNotFound { account_id: String },
+    /// The game can't be launched online because no account is selected
+    NoAccountSelected,
+    /// The account's tokens have expired and couldn't be refreshed, so it has to log in again
+    LoginRequired { account_id: String },
+    /// The API returned an error response or a response that couldn't be understood
+    Api {
+        message: String,
+        status: Option<u16>,
+    },
+    /// The network request failed before a response was received
+    Network { message: String },
+    /// The config file couldn't be read or written
+    ConfigIo { message: String },
+    /// The config file couldn't be parsed
+    ConfigParse { message: String },
+    /// The Minecraft version is not supported by the launcher
+    UnsupportedVersion { version: String },
+    /// A downloaded file did not have the expected hash
+    HashMismatch {
+        artifact: String,
+        expected: String,
+        actual: String,
+    },
+    /// The bundle can't be launched offline because some files haven't been downloaded
+    MissingArtifacts { missing: Vec<String> },
+    /// Launching the game failed
+    Launch { message: String },
+    /// An unexpected internal error
+    Internal { message: String },
+}
+
+impl CommandError {
+    /// Classify an error from reading or writing the config
+    pub fn config(err: anyhow::Error) -> Self {
+        if let Some(err) = Self::from_known(&err) {
+            return err;
+        }
+        let message = format!("{err:?}");
+        if err.chain().any(|x| x.is::<serde_json::Error>()) {
+            Self::ConfigParse { message }
+        } else {
+            Self::ConfigIo { message }
+        }
+    }
+
+    /// Classify an error from an API request
+    pub fn api(err: anyhow::Error) -> Self {
+        Self::from_known(&err).unwrap_or_else(|| Self::Api {
+            message: format!("{err:?}"),
+            status: None,
+        })
+    }
+
+    /// Classify an error from launching the game
+    pub fn launch(err: anyhow::Error) -> Self {
+        Self::from_known(&err).unwrap_or_else(|| Self::Launch {
+            message: format!("{err:?}"),
+        })
+    }
+
+    /// Create an internal error
+    pub fn internal(err: impl Display) -> Self {
+        Self::Internal {
+            message: err.to_string(),
+        }
+    }
+
+    /// Find an error kind from any of the errors in the chain that have a known type
+    fn from_known(err: &anyhow::Error) -> Option<Self> {
+        for cause in err.chain() {
+            if let Some(cause) = cause.downcast_ref::<Self>() {
+                return Some(cause.clone());
+            }
+            if let Some(cause) = cause.downcast_ref::<reqwest::Error>() {
+                let message = format!("{err:?}");
+                return Some(match cause.status() {
+                    Some(status) => Self::Api {
+                        message,
+                        status: Some(status.as_u16()),
+                    },
+                    None => Self::Network { message },
+                });
+            }
+            if let Some(cause) = cause.downcast_ref::<HashMismatchError>() {
+                return Some(Self::HashMismatch {
+                    artifact: cause.artifact.clone(),
+                    expected: cause.expected.clone(),
+                    actual: cause.actual.clone(),
+                });
+            }
+            if let Some(cause) = cause.downcast_ref::<MissingArtifactsError>() {
+                return Some(Self::MissingArtifacts {
+                    missing: cause.missing.clone(),
+                });
+            }
+            if let Some(cause) = cause.downcast_ref::<UnsupportedVersionError>() {
+                return Some(Self::UnsupportedVersion {
+                    version: cause.version.clone(),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BundleNotFound { bundle_id } => write!(f, "Bundle '{bundle_id}' does not exist"),
+            Self::PackNotInBundle { bundle_id, pack_id } => {
+                write!(f, "Pack '{pack_id}' is not in bundle '{bundle_id}'")
+            }
+            Self::DependencyConflicts { pack_id, conflicts } => {
+                let conflicts: Vec<_> = conflicts.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "Cannot add '{pack_id}' because of conflicting dependencies: {}",
+                    conflicts.join("; ")
+                )
+            }
+            Self::AccountNotFound { account_id } => {
+                write!(f, "Account '{account_id}' does not exist")
+            }
+            Self::NoAccountSelected => write!(f, "No account is selected"),
+            Self::LoginRequired { account_id } => {
+                write!(f, "Account '{account_id}' has to log in again")
+            }
+            Self::UnsupportedVersion { version } => {
+                write!(f, "Minecraft version {version} is not supported")
+            }
+            Self::HashMismatch {
+                artifact,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Hash mismatch for {artifact}: expected {expected}, got {actual}"
+            ),
+            Self::MissingArtifacts { missing } => write!(
+                f,
+                "Cannot launch offline because these have not been downloaded yet: {}",
+                missing.join(", ")
+            ),
+            Self::Api { message, .. }
+            | Self::Network { message }
+            | Self::ConfigIo { message }
+            | Self::ConfigParse { message }
+            | Self::Launch { message }
+            | Self::Internal { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let err = serde_json::from_str::<u32>("{").unwrap_err();
+        let err = anyhow::Error::from(err).context("Failed to parse config file");
+        assert!(matches!(
+            CommandError::config(err),
+            CommandError::ConfigParse { .. }
+        ));
+
+        let err = anyhow::Error::from(MissingArtifactsError {
+            missing: vec!["Paxi mod".into()],
+        })
+        .context("Failed to launch");
+        assert_eq!(
+            CommandError::launch(err),
+            CommandError::MissingArtifacts {
+                missing: vec!["Paxi mod".into()]
+            }
+        );
+
+        let err = anyhow::Error::from(CommandError::NoAccountSelected).context("Failed to update");
+        assert_eq!(CommandError::config(err), CommandError::NoAccountSelected);
+
+        let json = serde_json::to_value(CommandError::BundleNotFound {
+            bundle_id: "foo".into(),
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"kind": "bundle_not_found", "bundle_id": "foo"})
+        );
+    }
+}
@@ -1,39 +1,84 @@
+use std::collections::HashMap;
 use std::sync::Mutex;
+use std::time::Duration;
 
-use anyhow::anyhow;
 use directories::ProjectDirs;
 use mcvm::core::user::UserManager;
 use oauth2::ClientId;
 use reqwest::Client;
 use tauri::async_runtime;
 
+use crate::api::ApiClient;
+use crate::api_cache::{ApiCache, API_CACHE_MAX_AGE, API_CACHE_TTL};
+use crate::config::{ConfigStore, SmithedConfig};
 use crate::minecraft::auth::CLIENT_ID;
+use crate::minecraft::launch::{GameProcess, StopOutcome};
+use crate::pack_cache::PackCache;
+use crate::{http, paths};
+
+use self::status::GameStatus;
 
 pub mod commands;
+pub mod error;
+pub mod status;
 
 /// The shared tauri state
 pub struct SmithedState {
-    pub launched_game: Mutex<Option<LaunchedGame>>,
+    /// Games that have been launched, by bundle ID
+    pub launched_games: Mutex<HashMap<String, LaunchedGame>>,
+    /// Status of the games that aren't idle, by bundle ID
+    pub game_statuses: Mutex<HashMap<String, GameStatus>>,
     pub project_dirs: ProjectDirs,
+    pub config: ConfigStore,
     pub client: Client,
+    pub api: ApiClient,
+    pub pack_cache: PackCache,
     pub user_manager: Mutex<UserManager>,
 }
 
 impl SmithedState {
     pub fn new() -> anyhow::Result<Self> {
+        let project_dirs = paths::project_dirs()?;
+        if let Err(e) = paths::migrate_legacy_files(&project_dirs) {
+            eprintln!("Failed to migrate old config files: {e:?}");
+        }
+        let config = ConfigStore::new(&project_dirs);
+        let api_config = tauri::async_runtime::block_on(config.get()).unwrap_or_else(|e| {
+            eprintln!("Failed to load config, using the default API: {e:?}");
+            SmithedConfig::default()
+        });
+        let client = http::create_client()?;
+        let api_cache = ApiCache::new(paths::api_cache_dir(&project_dirs), API_CACHE_TTL);
+        if let Err(e) = api_cache.evict(API_CACHE_MAX_AGE) {
+            eprintln!("Failed to clean up the API cache: {e:?}");
+        }
         Ok(Self {
-            launched_game: Mutex::new(None),
-            project_dirs: ProjectDirs::from("dev.smithed", "Smithed", "smithed_launcher")
-                .ok_or(anyhow!("Failed to create project directories"))?,
-            client: Client::new(),
+            launched_games: Mutex::new(HashMap::new()),
+            game_statuses: Mutex::new(HashMap::new()),
+            pack_cache: PackCache::new(&project_dirs),
+            config,
+            project_dirs,
+            api: ApiClient::from_config(client.clone(), &api_config).with_cache(api_cache),
+            client,
             user_manager: Mutex::new(UserManager::new(ClientId::new(CLIENT_ID.to_string()))),
         })
     }
 }
 
-/// The current game launch process
+/// A game launch process for a bundle
 pub struct LaunchedGame {
     pub task_handle: LaunchHandle,
+    /// The game process, once it has been started
+    pub process: GameProcess,
+}
+
+impl LaunchedGame {
+    /// Stop the game, and the launch task in case it is still installing things
+    pub async fn stop(self, grace_period: Duration) -> anyhow::Result<StopOutcome> {
+        let outcome = self.process.stop(grace_period).await;
+        self.task_handle.abort();
+        outcome
+    }
 }
 
 /// Type for the task handle of the launch process
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use super::error::CommandError;
+
+/// Where the game is in its lifecycle, from launching to exiting
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum GameStatus {
+    /// No game has been launched
+    #[default]
+    Idle,
+    /// Setting up the bundle instance and the packs it needs
+    ResolvingPacks,
+    /// Installing the game files for the bundle's Minecraft version
+    UpdatingProfile,
+    /// Downloading and welding the bundle's packs
+    DownloadingPacks,
+    /// Installing the mods needed to load the packs
+    InstallingMods,
+    /// Logging in the user
+    Authenticating,
+    /// The game process is running
+    Running,
+    /// The game process exited. The code is missing if the process was killed by a signal.
+    Exited { code: Option<i32> },
+    /// Launching the game failed
+    Failed { error: CommandError },
+}
+
+/// An event for the game of a single bundle
+#[derive(Serialize, Clone, Debug)]
+pub struct GameEvent<T> {
+    pub bundle_id: String,
+    #[serde(flatten)]
+    pub event: T,
+}
+
+/// Event for when the game status changes
+pub const STATUS_CHANGED_EVENT: &str = "game_status_changed";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_serialization() {
+        assert_eq!(
+            serde_json::to_value(GameStatus::Exited { code: Some(1) }).unwrap(),
+            serde_json::json!({"status": "exited", "code": 1})
+        );
+        assert_eq!(
+            serde_json::to_value(GameEvent {
+                bundle_id: "foo".into(),
+                event: GameStatus::DownloadingPacks
+            })
+            .unwrap(),
+            serde_json::json!({"bundle_id": "foo", "status": "downloading_packs"})
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+/// A response that the stub server sends
+#[derive(Clone, Debug)]
+pub struct StubResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl StubResponse {
+    /// A successful response with a JSON body
+    pub fn json(value: &serde_json::Value) -> Self {
+        Self::bytes(value.to_string().into_bytes()).header("Content-Type", "application/json")
+    }
+
+    /// A successful response with a binary body
+    pub fn bytes(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    /// An empty response with a status code
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Add a header to the response
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// A request that the stub server received
+#[derive(Clone, Debug)]
+pub struct StubRequest {
+    pub path: String,
+    /// Headers with lowercase names
+    pub headers: HashMap<String, String>,
+}
+
+/// HTTP server that answers requests with the responses that were routed to their paths
+pub struct StubServer {
+    url: String,
+    state: Arc<Mutex<ServerState>>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    /// The responses for each path. The last one is repeated once the others are used up.
+    routes: HashMap<String, VecDeque<StubResponse>>,
+    requests: Vec<StubRequest>,
+}
+
+impl StubServer {
+    /// Start a server on a free local port
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind stub server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        let thread_state = state.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = thread_state.clone();
+                std::thread::spawn(move || handle(stream, &state));
+            }
+        });
+
+        Self { url, state }
+    }
+
+    /// The base URL of the server
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Add a response for a path. Responses for the same path are sent in order.
+    pub fn route(&self, path: &str, response: StubResponse) -> &Self {
+        let mut state = self.state.lock().unwrap();
+        state
+            .routes
+            .entry(path.into())
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    /// Get the requests that were made to a path
+    pub fn requests(&self, path: &str) -> Vec<StubRequest> {
+        let state = self.state.lock().unwrap();
+        state
+            .requests
+            .iter()
+            .filter(|request| request.path == path)
+            .cloned()
+            .collect()
+    }
+}
+
+fn handle(stream: TcpStream, state: &Mutex<ServerState>) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let response = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(StubRequest {
+            path: path.clone(),
+            headers,
+        });
+        match state.routes.get_mut(&path) {
+            Some(responses) if responses.len() > 1 => responses.pop_front(),
+            Some(responses) => responses.front().cloned(),
+            None => None,
+        }
+        .unwrap_or_else(|| StubResponse::status(404))
+    };
+
+    let mut out = format!(
+        "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{name}: {value}\r\n"));
+    }
+    out.push_str("\r\n");
+    let mut stream = &stream;
+    let _ = stream.write_all(out.as_bytes());
+    let _ = stream.write_all(&response.body);
+    let _ = stream.flush();
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api_types::{PackData, PackReference};
+
+/// Update info for a single pack in a bundle
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PackUpdate {
+    pub id: String,
+    /// The version that the bundle currently uses
+    pub current_version: String,
+    /// The newest version that supports the bundle's Minecraft version
+    pub newest_version: Option<String>,
+    pub update_available: bool,
+}
+
+/// Check whether a newer version of a pack is available for a Minecraft version
+pub fn check_update(pack: &PackReference, data: &PackData, minecraft_version: &str) -> PackUpdate {
+    let newest = data.get_newest_version(minecraft_version);
+    let position = |name: &str| data.versions.iter().position(|vers| vers.name == name);
+
+    let update_available = match newest {
+        Some(newest) if newest.name != pack.version => {
+            // Versions are ordered from oldest to newest, so never suggest a downgrade
+            match (position(&pack.version), position(&newest.name)) {
+                (Some(current), Some(newest)) => newest > current,
+                _ => true,
+            }
+        }
+        _ => false,
+    };
+
+    PackUpdate {
+        id: pack.id.clone(),
+        current_version: pack.version.clone(),
+        newest_version: newest.map(|vers| vers.name.clone()),
+        update_available,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api_types::{PackDisplay, PackVersion, PackVersionDownloads};
+
+    use super::*;
+
+    #[test]
+    fn test_check_update() {
+        let version = |name: &str, supports: &str| PackVersion {
+            name: name.into(),
+            downloads: PackVersionDownloads {
+                datapack: None,
+                resource_pack: None,
+            },
+            supports: vec![supports.into()],
+            dependencies: None,
+            hashes: None,
+        };
+        let data = PackData {
+            id: "foo".into(),
+            versions: vec![
+                version("1", "1.20.1"),
+                version("2", "1.20.1"),
+                version("3", "1.20.2"),
+            ],
+            display: PackDisplay {
+                name: "Foo".into(),
+                description: String::new(),
+                icon: String::new(),
+                hidden: false,
+                web_page: None,
+                urls: None,
+            },
+        };
+        let pack = |version: &str| PackReference {
+            id: "foo".into(),
+            version: version.into(),
+        };
+
+        let update = check_update(&pack("1"), &data, "1.20.1");
+        assert!(update.update_available);
+        assert_eq!(update.newest_version.as_deref(), Some("2"));
+
+        assert!(!check_update(&pack("2"), &data, "1.20.1").update_available);
+        assert!(!check_update(&pack("3"), &data, "1.20.1").update_available);
+        assert!(!check_update(&pack("1"), &data, "1.19").update_available);
+    }
+}
@@ -0,0 +1,191 @@
+use serde_json::{json, Map, Value};
+
+/// The result of merging multiple JSON files
+pub struct MergeResult {
+    pub value: Value,
+    /// Indices of the inputs that had contents which could not be merged
+    pub conflicting: Vec<usize>,
+}
+
+/// Merge pack.mcmeta files, using the highest pack format and combining filters and overlays
+pub fn merge_pack_meta(values: &[Value]) -> MergeResult {
+    let mut out = values[0].clone();
+    let Some(out_obj) = out.as_object_mut() else {
+        return MergeResult {
+            value: out,
+            conflicting: Vec::new(),
+        };
+    };
+
+    let pack_format = values
+        .iter()
+        .filter_map(|value| value.pointer("/pack/pack_format").and_then(Value::as_u64))
+        .max();
+    let pack = out_obj
+        .entry("pack")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Some(pack) = pack.as_object_mut() {
+        if let Some(pack_format) = pack_format {
+            pack.insert("pack_format".into(), json!(pack_format));
+        }
+        // The supported formats of a single pack don't apply to the welded pack
+        pack.remove("supported_formats");
+        pack.insert("description".into(), json!(WELDED_PACK_DESCRIPTION));
+    }
+
+    for pointer in ["/filter/block", "/overlays/entries"] {
+        let entries: Vec<Value> = values
+            .iter()
+            .filter_map(|value| value.pointer(pointer).and_then(Value::as_array))
+            .flatten()
+            .cloned()
+            .collect();
+        if entries.is_empty() {
+            continue;
+        }
+        let (section, key) = pointer[1..].split_once('/').expect("Pointer has two parts");
+        let section = out_obj
+            .entry(section)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(section) = section.as_object_mut() {
+            section.insert(key.into(), Value::Array(union(entries)));
+        }
+    }
+
+    MergeResult {
+        value: out,
+        conflicting: Vec::new(),
+    }
+}
+
+/// Merge tag files by taking the union of their values
+pub fn merge_tags(values: &[Value]) -> MergeResult {
+    let entries: Vec<Value> = values
+        .iter()
+        .filter_map(|value| value.get("values").and_then(Value::as_array))
+        .flatten()
+        .cloned()
+        .collect();
+    // Entries can either be plain IDs or objects with an ID, so we dedupe by the ID
+    let mut seen = Vec::new();
+    let mut merged = Vec::new();
+    for entry in entries {
+        let id = entry.get("id").unwrap_or(&entry).clone();
+        if !seen.contains(&id) {
+            seen.push(id);
+            merged.push(entry);
+        }
+    }
+
+    let replace = values
+        .iter()
+        .any(|value| value.get("replace").and_then(Value::as_bool) == Some(true));
+
+    let mut out = Map::new();
+    if replace {
+        out.insert("replace".into(), json!(true));
+    }
+    out.insert("values".into(), Value::Array(merged));
+
+    MergeResult {
+        value: Value::Object(out),
+        conflicting: Vec::new(),
+    }
+}
+
+/// Merge model files by combining their overrides.
+/// The rest of the model is taken from the first file, and the others are
+/// reported as conflicting if that part of them is different.
+pub fn merge_models(values: &[Value]) -> MergeResult {
+    let without_overrides = |value: &Value| {
+        let mut value = value.clone();
+        if let Some(obj) = value.as_object_mut() {
+            obj.remove("overrides");
+        }
+        value
+    };
+
+    let mut out = values[0].clone();
+    let base = without_overrides(&out);
+    let conflicting = values
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, value)| without_overrides(value) != base)
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut overrides = union(
+        values
+            .iter()
+            .filter_map(|value| value.get("overrides").and_then(Value::as_array))
+            .flatten()
+            .cloned()
+            .collect(),
+    );
+    if let Some(obj) = out.as_object_mut() {
+        if !overrides.is_empty() {
+            // The game uses the last matching override, so they need to be in ascending order
+            overrides.sort_by(|a, b| {
+                let cmd = |value: &Value| {
+                    value
+                        .pointer("/predicate/custom_model_data")
+                        .and_then(Value::as_f64)
+                        .unwrap_or(f64::MIN)
+                };
+                cmd(a).total_cmp(&cmd(b))
+            });
+            obj.insert("overrides".into(), Value::Array(overrides));
+        }
+    }
+
+    MergeResult {
+        value: out,
+        conflicting,
+    }
+}
+
+/// Remove duplicate values while keeping the first occurrence of each
+fn union(values: Vec<Value>) -> Vec<Value> {
+    let mut out = Vec::new();
+    for value in values {
+        if !out.contains(&value) {
+            out.push(value);
+        }
+    }
+    out
+}
+
+/// The description given to welded packs
+const WELDED_PACK_DESCRIPTION: &str = "Smithed Welded Pack";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_models() {
+        let a = json!({
+            "parent": "item/generated",
+            "overrides": [{"predicate": {"custom_model_data": 20}, "model": "a:twenty"}]
+        });
+        let b = json!({
+            "parent": "item/generated",
+            "overrides": [
+                {"predicate": {"custom_model_data": 10}, "model": "b:ten"},
+                {"predicate": {"custom_model_data": 20}, "model": "a:twenty"}
+            ]
+        });
+        let c = json!({"parent": "item/handheld"});
+
+        let result = merge_models(&[a, b, c]);
+        assert_eq!(result.conflicting, vec![2]);
+        assert_eq!(
+            result.value["overrides"],
+            json!([
+                {"predicate": {"custom_model_data": 10}, "model": "b:ten"},
+                {"predicate": {"custom_model_data": 20}, "model": "a:twenty"}
+            ])
+        );
+    }
+}
@@ -0,0 +1,248 @@
+mod merge;
+
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// A single pack zip to be welded together with others
+pub struct WeldInput {
+    /// The name of the pack, used for conflict reporting
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// The result of welding packs together
+pub struct WeldOutput {
+    /// The welded pack zip
+    pub data: Vec<u8>,
+    pub conflicts: Vec<WeldConflict>,
+}
+
+/// A file that could not be merged because multiple packs provided different versions of it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WeldConflict {
+    /// The path of the file inside the pack
+    pub path: String,
+    /// The pack whose version of the file was used
+    pub winner: String,
+    /// The packs whose versions of the file were discarded
+    pub overridden: Vec<String>,
+}
+
+/// Weld multiple pack zips into a single pack zip.
+/// Files from packs earlier in the list take priority when they can't be merged.
+pub fn weld(packs: &[WeldInput]) -> anyhow::Result<WeldOutput> {
+    // Collect the files from every pack, keeping them in pack order
+    let mut files: BTreeMap<String, Vec<(&str, Vec<u8>)>> = BTreeMap::new();
+    for pack in packs {
+        let mut zip = ZipArchive::new(Cursor::new(&pack.data))
+            .with_context(|| format!("Failed to open pack '{}'", pack.name))?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let path = file.name().replace('\\', "/");
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)
+                .with_context(|| format!("Failed to read '{path}' in pack '{}'", pack.name))?;
+            files
+                .entry(path)
+                .or_default()
+                .push((pack.name.as_str(), contents));
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    let mut out = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (path, sources) in files {
+        let contents = merge_file(&path, sources, &mut conflicts)?;
+        out.start_file(path.as_str(), options)?;
+        out.write_all(&contents)?;
+    }
+    let data = out.finish()?.into_inner();
+
+    Ok(WeldOutput { data, conflicts })
+}
+
+/// Merge all of the versions of a single file
+fn merge_file(
+    path: &str,
+    mut sources: Vec<(&str, Vec<u8>)>,
+    conflicts: &mut Vec<WeldConflict>,
+) -> anyhow::Result<Vec<u8>> {
+    if sources.len() == 1 {
+        return Ok(sources.remove(0).1);
+    }
+
+    let merger: Option<MergeFn> = match FileKind::of(path) {
+        FileKind::PackMeta => Some(merge::merge_pack_meta),
+        FileKind::Tag => Some(merge::merge_tags),
+        FileKind::Model => Some(merge::merge_models),
+        FileKind::Other => None,
+    };
+
+    if let Some(merger) = merger {
+        let values: Result<Vec<serde_json::Value>, _> = sources
+            .iter()
+            .map(|(_, contents)| serde_json::from_slice(contents))
+            .collect();
+        // Files that aren't valid JSON fall back to the first pack winning
+        if let Ok(values) = values {
+            let result = merger(&values);
+            if !result.conflicting.is_empty() {
+                conflicts.push(WeldConflict {
+                    path: path.to_string(),
+                    winner: sources[0].0.to_string(),
+                    overridden: result
+                        .conflicting
+                        .iter()
+                        .map(|i| sources[*i].0.to_string())
+                        .collect(),
+                });
+            }
+            return serde_json::to_vec_pretty(&result.value)
+                .with_context(|| format!("Failed to serialize merged '{path}'"));
+        }
+    }
+
+    let (winner, contents) = sources.remove(0);
+    let overridden: Vec<String> = sources
+        .iter()
+        .filter(|(_, other)| *other != contents)
+        .map(|(name, _)| name.to_string())
+        .collect();
+    if !overridden.is_empty() {
+        conflicts.push(WeldConflict {
+            path: path.to_string(),
+            winner: winner.to_string(),
+            overridden,
+        });
+    }
+
+    Ok(contents)
+}
+
+/// A function that merges the parsed contents of multiple JSON files
+type MergeFn = fn(&[serde_json::Value]) -> merge::MergeResult;
+
+/// The kinds of files that have special merging rules
+#[derive(Debug, PartialEq)]
+enum FileKind {
+    PackMeta,
+    Tag,
+    Model,
+    Other,
+}
+
+impl FileKind {
+    fn of(path: &str) -> Self {
+        if path == "pack.mcmeta" {
+            return Self::PackMeta;
+        }
+        if !path.ends_with(".json") {
+            return Self::Other;
+        }
+        let parts: Vec<_> = path.split('/').collect();
+        match parts.as_slice() {
+            ["data", _, "tags", _, ..] => Self::Tag,
+            ["assets", _, "models", _, ..] => Self::Model,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_pack(name: &str, files: &[(&str, &str)]) -> WeldInput {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, contents) in files {
+            zip.start_file(*path, FileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        WeldInput {
+            name: name.into(),
+            data: zip.finish().unwrap().into_inner(),
+        }
+    }
+
+    fn read_file(data: &[u8], path: &str) -> String {
+        let mut zip = ZipArchive::new(Cursor::new(data)).unwrap();
+        let mut file = zip.by_name(path).unwrap();
+        let mut out = String::new();
+        file.read_to_string(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_file_kind() {
+        assert_eq!(FileKind::of("pack.mcmeta"), FileKind::PackMeta);
+        assert_eq!(
+            FileKind::of("data/minecraft/tags/functions/load.json"),
+            FileKind::Tag
+        );
+        assert_eq!(
+            FileKind::of("assets/minecraft/models/item/stick.json"),
+            FileKind::Model
+        );
+        assert_eq!(
+            FileKind::of("data/foo/functions/load.mcfunction"),
+            FileKind::Other
+        );
+    }
+
+    #[test]
+    fn test_weld() {
+        let tag = "data/minecraft/tags/functions/load.json";
+        let a = make_pack(
+            "a",
+            &[
+                (
+                    "pack.mcmeta",
+                    r#"{"pack":{"pack_format":10,"description":"A"}}"#,
+                ),
+                (tag, r#"{"values":["a:load"]}"#),
+                ("data/a/functions/load.mcfunction", "say a"),
+                ("data/shared/functions/util.mcfunction", "say a"),
+            ],
+        );
+        let b = make_pack(
+            "b",
+            &[
+                (
+                    "pack.mcmeta",
+                    r#"{"pack":{"pack_format":15,"description":"B"}}"#,
+                ),
+                (tag, r#"{"values":["b:load","a:load"]}"#),
+                ("data/shared/functions/util.mcfunction", "say b"),
+            ],
+        );
+
+        let out = weld(&[a, b]).unwrap();
+
+        let tag: serde_json::Value = serde_json::from_str(&read_file(&out.data, tag)).unwrap();
+        assert_eq!(tag["values"], serde_json::json!(["a:load", "b:load"]));
+        let meta: serde_json::Value =
+            serde_json::from_str(&read_file(&out.data, "pack.mcmeta")).unwrap();
+        assert_eq!(meta["pack"]["pack_format"], 15);
+        assert_eq!(
+            read_file(&out.data, "data/a/functions/load.mcfunction"),
+            "say a"
+        );
+        assert_eq!(
+            out.conflicts,
+            vec![WeldConflict {
+                path: "data/shared/functions/util.mcfunction".into(),
+                winner: "a".into(),
+                overridden: vec!["b".into()],
+            }]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 import { ChooseBox, IconTextButton, svg } from "components"
 import { useEffect, useState } from "react"
 import { ChooseBoxChoice, LocalBundleConfig } from "../types"
-import { getChooseBoxBundles } from "../util"
+import { commandErrorMessage, getChooseBoxBundles } from "../util"
 import { invoke } from "@tauri-apps/api"
 import { PackReference } from "data-types"
 
@@ -29,7 +29,9 @@ function AddToBundle({ onFinish, packId }: AddToBundleProps) {
 			)
 			return newestVersion
 		} catch (e) {
-			console.error("Failed to check for pack support: " + e)
+			console.error(
+				"Failed to check for pack support: " + commandErrorMessage(e)
+			)
 			return undefined
 		}
 	}
@@ -41,7 +43,9 @@ function AddToBundle({ onFinish, packId }: AddToBundleProps) {
 			})
 			return bundle.packs.find((pack) => pack.id == packId) !== undefined
 		} catch (e) {
-			console.error("Failed to check if bundle exists: " + e)
+			console.error(
+				"Failed to check if bundle exists: " + commandErrorMessage(e)
+			)
 			return true
 		}
 	}
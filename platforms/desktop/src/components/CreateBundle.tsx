@@ -3,6 +3,7 @@ import { MinecraftVersion } from "data-types"
 import { availableMinecraftVersionsChooseBox } from "../types"
 import { invoke } from "@tauri-apps/api"
 import { useState } from "react"
+import { commandErrorMessage } from "../util"
 
 function CreateBundle({ onFinish }: CreateBundleProps) {
 	const [name, setName] = useState("")
@@ -18,7 +19,9 @@ function CreateBundle({ onFinish }: CreateBundleProps) {
 			})
 			return exists
 		} catch (e) {
-			console.error("Failed to check if bundle exists: " + e)
+			console.error(
+				"Failed to check if bundle exists: " + commandErrorMessage(e)
+			)
 			return true
 		}
 	}
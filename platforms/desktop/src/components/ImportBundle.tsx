@@ -1,7 +1,7 @@
 import { ChooseBox, IconInput, IconTextButton, svg } from "components"
 import { useEffect, useState } from "react"
 import { ChooseBoxChoice, LocalBundleConfig } from "../types"
-import { getChooseBoxBundles } from "../util"
+import { commandErrorMessage, getChooseBoxBundles } from "../util"
 import { invoke } from "@tauri-apps/api"
 import { PackBundle } from "data-types"
 
@@ -33,7 +33,9 @@ function ImportBundle({ bundleId, onFinish }: ImportBundleProps) {
 			})
 			return exists
 		} catch (e) {
-			console.error("Failed to check if bundle exists: " + e)
+			console.error(
+				"Failed to check if bundle exists: " + commandErrorMessage(e)
+			)
 			return true
 		}
 	}
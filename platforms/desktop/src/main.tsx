@@ -6,10 +6,13 @@ import ClientParent, { subRoutes } from "client"
 import { IconTextButton, NavButton, svg } from "components"
 import AddToBundle from "./components/AddToBundle"
 import { invoke } from "@tauri-apps/api"
+import { message } from "@tauri-apps/api/dialog"
 import { PackReference } from "data-types"
 import EditLocalBundle from "./pages/EditLocalBundle"
 import ImportBundle from "./components/ImportBundle"
 import { defaultContext, IClientContext } from "client/src/context"
+import { DependencyResolution } from "./types"
+import { commandErrorMessage } from "./util"
 
 // Injection code to modify the client so it works with the launcher
 const launchRoute = {
@@ -62,14 +65,32 @@ const context: IClientContext = {
 									version: packVersion,
 								}
 								try {
-									await invoke("add_pack_to_bundle", {
-										bundleId: bundleId,
-										pack: ref,
-									})
+									const resolution: DependencyResolution =
+										await invoke("add_pack_to_bundle", {
+											bundleId: bundleId,
+											pack: ref,
+										})
+									if (resolution.substituted.length > 0) {
+										const substituted = resolution.substituted
+											.map(
+												(x) =>
+													`'${x.pack_id}' version ${x.selected} instead of ${x.requested}`
+											)
+											.join(", ")
+										await message(
+											`Added ${substituted} because the requested versions do not support this bundle's Minecraft version`,
+											{ title: "Pack added" }
+										)
+									}
 								} catch (e) {
+									const msg = commandErrorMessage(e)
 									console.error(
-										"Failed to add pack to bundle: " + e
+										"Failed to add pack to bundle: " + msg
 									)
+									await message(msg, {
+										title: "Failed to add pack to bundle",
+										type: "error",
+									})
 								}
 							}
 							closePopup()
@@ -102,7 +123,8 @@ const context: IClientContext = {
 									})
 								} catch (e) {
 									console.error(
-										"Failed to add imported bundle: " + e
+										"Failed to add imported bundle: " +
+											commandErrorMessage(e)
 									)
 								}
 							}
@@ -2,15 +2,16 @@ import "./EditLocalBundle.css"
 
 import { invoke } from "@tauri-apps/api"
 import { useNavigate, useParams } from "react-router-dom"
-import { LocalBundleConfig } from "../types"
+import { BundlePack, CommandError, LocalBundleConfig } from "../types"
 import { IconTextButton, PackCard, svg } from "components"
 import { PackData, PackReference } from "data-types"
 import { useEffect, useState } from "react"
 import BackButton from "client/src/widget/BackButton"
+import { commandErrorMessage } from "../util"
 
 interface BundleData {
 	bundle: LocalBundleConfig
-	packs: [PackReference, PackData][]
+	packs: BundlePack[]
 }
 
 function EditLocalBundle({}: EditLocalBundleProps) {
@@ -26,7 +27,7 @@ function EditLocalBundle({}: EditLocalBundleProps) {
 				const bundle: LocalBundleConfig = await invoke("get_bundle", {
 					bundleId: bundleId,
 				})
-				const packs: [PackReference, PackData][] = await invoke(
+				const packs: BundlePack[] = await invoke(
 					"get_bundle_packs",
 					{
 						bundleId: bundleId,
@@ -34,7 +35,9 @@ function EditLocalBundle({}: EditLocalBundleProps) {
 				)
 				setData({ bundle, packs })
 			} catch (e) {
-				console.error("Failed to get local bundle data: " + e)
+				console.error(
+					"Failed to get local bundle data: " + commandErrorMessage(e)
+				)
 			}
 		}
 
@@ -50,7 +53,7 @@ function EditLocalBundle({}: EditLocalBundleProps) {
 			await invoke("remove_bundle", { bundleId: bundleId })
 			navigate("/launch")
 		} catch (e) {
-			console.error("Failed to delete bundle: " + e)
+			console.error("Failed to delete bundle: " + commandErrorMessage(e))
 		}
 	}
 
@@ -62,21 +65,32 @@ function EditLocalBundle({}: EditLocalBundleProps) {
 			})
 			setReload(true)
 		} catch (e) {
-			console.error("Failed to delete pack from bundle: " + e)
+			console.error(
+				"Failed to delete pack from bundle: " + commandErrorMessage(e)
+			)
 		}
 	}
 
 	let packElems: JSX.Element[] = []
 	if (data !== undefined) {
-		for (let [reference, packData] of data.packs) {
+		for (let { reference, data: packData, error } of data.packs) {
+			const onRemove = () => {
+				removePack(reference.id)
+			}
 			packElems.push(
-				<EditLocalPack
-					data={packData}
-					reference={reference}
-					onRemove={() => {
-						removePack(reference.id)
-					}}
-				/>
+				packData !== null ? (
+					<EditLocalPack
+						data={packData}
+						reference={reference}
+						onRemove={onRemove}
+					/>
+				) : (
+					<FailedLocalPack
+						reference={reference}
+						error={error}
+						onRemove={onRemove}
+					/>
+				)
 			)
 		}
 	}
@@ -216,4 +230,55 @@ interface EditLocalPackProps {
 	onRemove: () => void
 }
 
+// A pack in the bundle that couldn't be loaded, which can still be removed
+function FailedLocalPack({ reference, error, onRemove }: FailedLocalPackProps) {
+	return (
+		<div className="container editLocalPackContainer">
+			<div className="container editLocalPackTitleContainer closed">
+				<div
+					className="container"
+					style={{
+						justifyContent: "left",
+						flexGrow: "1",
+						gap: "1rem",
+						flexDirection: "row",
+						boxSizing: "border-box",
+					}}
+				>
+					<div className="editLocalPackName">{reference.id}</div>
+					<div className="editLocalPackVersion">
+						v{reference.version}
+					</div>
+					<div className="editLocalPackError">
+						{packErrorMessage(error)}
+					</div>
+				</div>
+				<IconTextButton
+					className="disturbingButtonLike"
+					text="Remove from bundle"
+					icon={svg.Cross}
+					style={{ width: "fit-content" }}
+					onClick={onRemove}
+				/>
+			</div>
+		</div>
+	)
+}
+
+interface FailedLocalPackProps {
+	reference: PackReference
+	error: CommandError | null
+	onRemove: () => void
+}
+
+function packErrorMessage(error: CommandError | null) {
+	if (error?.kind === "api" && error.status === 404) {
+		return "This pack no longer exists on Smithed"
+	}
+	if (error?.kind === "network") {
+		return "Could not reach Smithed"
+	}
+	return "Failed to load this pack"
+}
+
 export default EditLocalBundle
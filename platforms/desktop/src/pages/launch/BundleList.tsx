@@ -6,6 +6,7 @@ import libraries_box from "client/src/assets/libraries_box.png"
 import { IconTextButton, svg } from "components"
 import { MinecraftVersion } from "data-types"
 import CreateBundle from "../../components/CreateBundle"
+import { commandErrorMessage } from "../../util"
 
 function BundleList({ onSelect }: BundleListProps) {
 	const [list, setList] = useState<ConfiguredLocalBundles | undefined>(
@@ -30,7 +31,10 @@ function BundleList({ onSelect }: BundleListProps) {
 					})
 					setBundleToAdd(undefined)
 				} catch (e) {
-					console.error("Failed to get local bundle list: " + e)
+					console.error(
+						"Failed to get local bundle list: " +
+							commandErrorMessage(e)
+					)
 				}
 			}
 			try {
@@ -38,7 +42,9 @@ function BundleList({ onSelect }: BundleListProps) {
 					await invoke("list_bundles")
 				setList(newList)
 			} catch (e) {
-				console.error("Failed to get local bundle list: " + e)
+				console.error(
+					"Failed to get local bundle list: " + commandErrorMessage(e)
+				)
 			}
 		}
 
@@ -52,13 +52,31 @@ export interface LaunchConsoleProps {
 }
 
 export function createDefaultMessage(event: OutputMessageEvent) {
-	return event
+	return event.message
 }
 
 export function createProgressBar(event: AssociatedProgressEvent) {
+	if (event.unit === "bytes") {
+		const current = formatBytes(event.current)
+		const progress =
+			event.total === null
+				? current
+				: `${current}/${formatBytes(event.total)}`
+		return `(${progress}) ${event.message}`
+	}
 	return `(${event.current}/${event.total}) ${event.message}`
 }
 
+function formatBytes(bytes: number) {
+	const units = ["B", "KB", "MB", "GB"]
+	let unit = 0
+	while (bytes >= 1024 && unit < units.length - 1) {
+		bytes /= 1024
+		unit++
+	}
+	return `${unit === 0 ? bytes : bytes.toFixed(1)} ${units[unit]}`
+}
+
 function consoleMessage(msg: string) {
 	return
 }
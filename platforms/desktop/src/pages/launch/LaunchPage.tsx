@@ -13,11 +13,12 @@ import {
 } from "../../types"
 import { MinecraftVersion } from "data-types"
 import { Smithed } from "components/svg"
-import { getChooseBoxBundles } from "../../util"
+import { commandErrorMessage, getChooseBoxBundles } from "../../util"
 import BundleList from "./BundleList"
 import CreateBundle from "../../components/CreateBundle"
 import LaunchConsole, {
 	LaunchConsoleProps,
+	createDefaultMessage,
 	createProgressBar,
 } from "./LaunchConsole"
 
@@ -68,8 +69,16 @@ function LaunchFooter({ selectedBundle, onSetAuthDisplay }: LaunchFooterProps) {
 
 	const [error, setError] = useState<undefined | "no_bundle">(undefined)
 	const [unlistens, setUnlistens] = useState<UnlistenFn[]>([])
+	const [runningBundle, setRunningBundle] = useState<string | undefined>(
+		undefined
+	)
+
+	// The download that the last console message of each bundle shows the
+	// progress of
+	const lastProgress = useRef<Map<string, string>>(new Map())
 
-	function updateConsole(msg: string) {
+	function updateConsole(bundleId: string, msg: string) {
+		lastProgress.current.delete(bundleId)
 		setConsoleProps((current) => {
 			return {
 				messages: current.messages.concat(msg),
@@ -77,12 +86,35 @@ function LaunchFooter({ selectedBundle, onSetAuthDisplay }: LaunchFooterProps) {
 		})
 	}
 
+	function updateProgress(bundleId: string, event: AssociatedProgressEvent) {
+		const msg = createProgressBar(event)
+		// Download progress comes in often, so it replaces the line of the same
+		// download
+		const replace =
+			event.unit === "bytes" &&
+			lastProgress.current.get(bundleId) === event.message
+		if (event.unit === "bytes") {
+			lastProgress.current.set(bundleId, event.message)
+		} else {
+			lastProgress.current.delete(bundleId)
+		}
+		setConsoleProps((current) => {
+			const messages = replace
+				? current.messages.slice(0, -1)
+				: current.messages
+			return {
+				messages: messages.concat(msg),
+			}
+		})
+	}
+
 	function clearConsole() {
+		lastProgress.current.clear()
 		let console: LaunchConsoleProps = { messages: [] }
 		setConsoleProps(console)
 	}
 
-	async function launchGame() {
+	async function launchGame(bundleId: string) {
 		console.log("3 2 1 blastoff!")
 		try {
 			for (let unlisten of unlistens) {
@@ -90,19 +122,31 @@ function LaunchFooter({ selectedBundle, onSetAuthDisplay }: LaunchFooterProps) {
 			}
 			setShowConsole(true)
 			clearConsole()
+			setRunningBundle(bundleId)
 			let launchPromise = invoke("launch_game", {
-				bundleId: selectedBundle,
+				bundleId: bundleId,
 				offline: !online,
 			})
 
-			let closeListenerPromise = listen("game_finished", () => {
-				onSetAuthDisplay(undefined)
-				setShowConsole(false)
-			})
+			let closeListenerPromise = listen(
+				"game_finished",
+				(event: Event<string>) => {
+					if (event.payload !== bundleId) {
+						return
+					}
+					onSetAuthDisplay(undefined)
+					setShowConsole(false)
+				}
+			)
 
+			// Other bundles can be running at the same time, so only the
+			// output of this one is shown
 			let auth = listen(
 				"mcvm_display_auth_info",
 				(event: Event<AuthDisplayEvent>) => {
+					if (event.payload.bundle_id !== bundleId) {
+						return
+					}
 					let url = event.payload.url
 					let device_code = event.payload.device_code
 					onSetAuthDisplay({ url, device_code })
@@ -112,21 +156,30 @@ function LaunchFooter({ selectedBundle, onSetAuthDisplay }: LaunchFooterProps) {
 			let message = listen(
 				"mcvm_output_message",
 				(event: Event<OutputMessageEvent>) => {
-					updateConsole(event.payload)
+					if (event.payload.bundle_id !== bundleId) {
+						return
+					}
+					updateConsole(bundleId, createDefaultMessage(event.payload))
 				}
 			)
 
 			let header = listen(
 				"mcvm_output_header",
 				(event: Event<OutputMessageEvent>) => {
-					updateConsole(event.payload)
+					if (event.payload.bundle_id !== bundleId) {
+						return
+					}
+					updateConsole(bundleId, createDefaultMessage(event.payload))
 				}
 			)
 
 			let progress = listen(
 				"mcvm_output_progress",
 				(event: Event<AssociatedProgressEvent>) => {
-					updateConsole(createProgressBar(event.payload))
+					if (event.payload.bundle_id !== bundleId) {
+						return
+					}
+					updateProgress(bundleId, event.payload)
 				}
 			)
 
@@ -140,7 +193,7 @@ function LaunchFooter({ selectedBundle, onSetAuthDisplay }: LaunchFooterProps) {
 			])
 			setUnlistens(eventUnlistens)
 		} catch (e) {
-			console.error("Failed to launch game: " + e)
+			console.error("Failed to launch game: " + commandErrorMessage(e))
 		}
 	}
 
@@ -149,7 +202,7 @@ function LaunchFooter({ selectedBundle, onSetAuthDisplay }: LaunchFooterProps) {
 			setError("no_bundle")
 			return
 		}
-		await launchGame()
+		await launchGame(selectedBundle)
 	}
 
 	async function stopGame() {
@@ -157,9 +210,11 @@ function LaunchFooter({ selectedBundle, onSetAuthDisplay }: LaunchFooterProps) {
 		onSetAuthDisplay(undefined)
 		setShowConsole(false)
 		try {
-			await invoke("stop_game")
+			await invoke("stop_game", {
+				bundleId: runningBundle ?? selectedBundle,
+			})
 		} catch (e) {
-			console.error("Failed to stop game: " + e)
+			console.error("Failed to stop game: " + commandErrorMessage(e))
 		}
 	}
 
@@ -355,7 +410,7 @@ function BundleSelector({ onSetBundle }: BundleSelectorProps) {
 				let bundle: LocalBundleConfig = { version: version, packs: [] }
 				await invoke("add_bundle", { bundleId: name, bundle: bundle })
 			} catch (e) {
-				console.error("Failed to add bundle: " + e)
+				console.error("Failed to add bundle: " + commandErrorMessage(e))
 			}
 		}
 
@@ -391,6 +446,7 @@ interface BundleSelectorProps {
 }
 
 interface AuthDisplayEvent {
+	bundle_id?: string
 	url: string
 	device_code: string
 }
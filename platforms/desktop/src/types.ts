@@ -2,6 +2,7 @@
 
 import {
 	MinecraftVersion,
+	PackData,
 	PackReference,
 	supportedMinecraftVersions,
 } from "data-types"
@@ -15,6 +16,25 @@ export interface LocalBundleConfig {
 	packs: PackReference[]
 }
 
+export interface BundlePack {
+	reference: PackReference
+	data: PackData | null
+	error: CommandError | null
+}
+
+export interface PackUpdate {
+	id: string
+	current_version: string
+	newest_version: string | null
+	update_available: boolean
+}
+
+export interface PackUpdateCheck {
+	id: string
+	update: PackUpdate | null
+	error: CommandError | null
+}
+
 export const availableMinecraftVersionsChooseBox: ChooseBoxChoice[] =
 	supportedMinecraftVersions.map((version) => {
 		return { content: version, value: version }
@@ -22,10 +42,113 @@ export const availableMinecraftVersionsChooseBox: ChooseBoxChoice[] =
 
 export type ChooseBoxChoice = { value: string; content: string }
 
+// Output events have the ID of the bundle that is being launched, if any
 export interface AssociatedProgressEvent {
+	bundle_id?: string
 	current: number
-	total: number
+	total: number | null
+	message: string
+	unit: "steps" | "bytes"
+}
+
+export interface OutputMessageEvent {
+	bundle_id?: string
+	message: string
+}
+
+export type CommandError =
+	| { kind: "bundle_not_found"; bundle_id: string }
+	| { kind: "pack_not_in_bundle"; bundle_id: string; pack_id: string }
+	| {
+			kind: "dependency_conflicts"
+			pack_id: string
+			conflicts: DependencyConflict[]
+	  }
+	| { kind: "account_not_found"; account_id: string }
+	| { kind: "no_account_selected" }
+	| { kind: "login_required"; account_id: string }
+	| { kind: "api"; message: string; status: number | null }
+	| { kind: "network"; message: string }
+	| { kind: "config_io"; message: string }
+	| { kind: "config_parse"; message: string }
+	| { kind: "unsupported_version"; version: string }
+	| {
+			kind: "hash_mismatch"
+			artifact: string
+			expected: string
+			actual: string
+	  }
+	| { kind: "missing_artifacts"; missing: string[] }
+	| { kind: "launch"; message: string }
+	| { kind: "internal"; message: string }
+
+export interface DependencyResolution {
+	added: PackReference[]
+	conflicts: DependencyConflict[]
+	substituted: VersionSubstitution[]
+	cycles: string[][]
+}
+
+export type DependencyConflict =
+	| {
+			kind: "version_mismatch"
+			pack_id: string
+			selected: string
+			required: string
+			required_by: string | null
+	  }
+	| { kind: "no_compatible_version"; pack_id: string; required_by: string | null }
+	| { kind: "unavailable"; pack_id: string; required_by: string | null }
+
+export interface VersionSubstitution {
+	pack_id: string
+	requested: string
+	selected: string
+}
+
+export type GameStatus =
+	| { status: "idle" }
+	| { status: "resolving_packs" }
+	| { status: "updating_profile" }
+	| { status: "downloading_packs" }
+	| { status: "installing_mods" }
+	| { status: "authenticating" }
+	| { status: "running" }
+	| { status: "exited"; code: number | null }
+	| { status: "failed"; error: CommandError }
+
+export type GameStatusChangedEvent = { bundle_id: string } & GameStatus
+
+export type LogLevel =
+	| "trace"
+	| "debug"
+	| "info"
+	| "warn"
+	| "error"
+	| "fatal"
+	| "unknown"
+
+export interface GameLogEvent {
+	bundle_id: string
+	stream: "stdout" | "stderr" | "log_file"
+	level: LogLevel
+	time: string | null
+	thread: string | null
+	logger: string | null
 	message: string
 }
 
-export type OutputMessageEvent = string
+export interface GameCrashedEvent {
+	bundle_id: string
+	exit_code: number | null
+	summary: string
+	crash_report: string | null
+	suspected_mods: string[]
+}
+
+export interface AccountInfo {
+	id: string
+	name: string
+	uuid: string | null
+	selected: boolean
+}
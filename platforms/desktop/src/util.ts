@@ -1,5 +1,10 @@
 import { invoke } from "@tauri-apps/api"
-import { ChooseBoxChoice, ConfiguredLocalBundles } from "./types"
+import {
+	ChooseBoxChoice,
+	CommandError,
+	ConfiguredLocalBundles,
+	DependencyConflict,
+} from "./types"
 
 export async function getChooseBoxBundles() {
 	try {
@@ -11,8 +16,66 @@ export async function getChooseBoxBundles() {
 
 		return choicesMapped
 	} catch (e) {
-		console.error("Failed to get available bundles: " + e)
+		console.error(
+			"Failed to get available bundles: " + commandErrorMessage(e)
+		)
 	}
 
 	return []
 }
+
+// Get a message to show for an error thrown by a command
+export function commandErrorMessage(e: unknown) {
+	if (typeof e !== "object" || e === null || !("kind" in e)) {
+		return String(e)
+	}
+	const error = e as CommandError
+	switch (error.kind) {
+		case "bundle_not_found":
+			return `Bundle '${error.bundle_id}' does not exist`
+		case "pack_not_in_bundle":
+			return `Pack '${error.pack_id}' is not in bundle '${error.bundle_id}'`
+		case "dependency_conflicts":
+			return (
+				`Cannot add '${error.pack_id}' because of conflicting dependencies: ` +
+				error.conflicts.map(dependencyConflictMessage).join("; ")
+			)
+		case "account_not_found":
+			return `Account '${error.account_id}' does not exist`
+		case "no_account_selected":
+			return "No account is selected"
+		case "login_required":
+			return `Account '${error.account_id}' has to log in again`
+		case "unsupported_version":
+			return `Minecraft version ${error.version} is not supported`
+		case "hash_mismatch":
+			return `Hash mismatch for ${error.artifact}: expected ${error.expected}, got ${error.actual}`
+		case "missing_artifacts":
+			return (
+				"Cannot launch offline because these have not been downloaded yet: " +
+				error.missing.join(", ")
+			)
+		case "api":
+		case "network":
+		case "config_io":
+		case "config_parse":
+		case "launch":
+		case "internal":
+			return error.message
+	}
+}
+
+function dependencyConflictMessage(conflict: DependencyConflict) {
+	const requiredBy =
+		conflict.required_by === null
+			? ""
+			: ` (required by '${conflict.required_by}')`
+	switch (conflict.kind) {
+		case "version_mismatch":
+			return `'${conflict.pack_id}' is at version ${conflict.selected} but version ${conflict.required} is needed${requiredBy}`
+		case "no_compatible_version":
+			return `'${conflict.pack_id}' has no version for this Minecraft version${requiredBy}`
+		case "unavailable":
+			return `'${conflict.pack_id}' could not be found${requiredBy}`
+	}
+}